@@ -10,6 +10,8 @@ use proc_macro::TokenStream;
 use darling::FromMeta;
 use syn::{parse_macro_input, ItemStruct};
 
+use crate::utility::GeneratorResult;
+
 mod model;
 mod utility;
 
@@ -34,6 +36,26 @@ macro_rules! parse_nested_meta {
 struct ModelArgs {
     coll_name: String,
     observer: Option<()>,
+    after_load: Option<()>,
+    soft_delete: Option<()>,
+    validate: Option<()>,
+    created_field: Option<String>,
+    updated_field: Option<String>,
+}
+
+/// the arguments of `#[rspark(...)]` for `#[derive(RsparkModel)]` , the fields are the names
+/// of the struct fields , the id and timestamp fields must be declared by the struct
+#[derive(FromMeta, Debug)]
+struct DeriveArgs {
+    coll_name: String,
+    observer: Option<()>,
+    after_load: Option<()>,
+    soft_delete: Option<()>,
+    validate: Option<()>,
+    id_field: Option<String>,
+    created_field: Option<String>,
+    updated_field: Option<String>,
+    deleted_field: Option<String>,
 }
 
 #[proc_macro_attribute]
@@ -46,3 +68,42 @@ pub fn Model(args: TokenStream, item: TokenStream) -> TokenStream {
         Err(err) => err.write_errors().into(),
     }
 }
+
+/// like the `Model` attribute but for a struct that declares its own id and timestamp fields ,
+/// it generates the same traits and `new_model` without changing the struct
+/// ```ignore
+/// #[derive(RsparkModel, Serialize, Deserialize, Default, Debug)]
+/// #[rspark(coll_name = "users", created_field = "inserted_at")]
+/// struct User {
+///     #[serde(skip_serializing_if = "Option::is_none")]
+///     _id: Option<ObjectId>,
+///     inserted_at: Option<DateTime>,
+///     updated_at: Option<DateTime>,
+///     #[index]
+///     name: String,
+/// }
+/// ```
+#[proc_macro_derive(RsparkModel, attributes(rspark, index))]
+pub fn rspark_model(item: TokenStream) -> TokenStream {
+    let __struct = parse_macro_input!(item as ItemStruct);
+    let generated =
+        derive_args(&__struct).and_then(|args| model::generate_derive(&__struct, &args));
+    match generated {
+        Ok(expanded) => expanded,
+        Err(err) => err.write_errors().into(),
+    }
+}
+
+/// reads the `#[rspark(...)]` attribute of the struct
+fn derive_args(__struct: &ItemStruct) -> GeneratorResult<DeriveArgs> {
+    let Some(attr) = __struct.attrs.iter().find(|attr| attr.path().is_ident("rspark")) else {
+        return Err(syn::Error::new_spanned(
+            &__struct.ident,
+            "RsparkModel needs the collection name , like #[rspark(coll_name = \"users\")]",
+        )
+        .into());
+    };
+    let list = attr.meta.require_list()?;
+    let meta = darling::ast::NestedMeta::parse_meta_list(list.tokens.clone())?;
+    Ok(DeriveArgs::from_list(&meta)?)
+}
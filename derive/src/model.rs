@@ -2,14 +2,21 @@ use darling::FromMeta;
 use proc_macro::TokenStream;
 
 use crate::utility::GeneratorResult;
-use crate::ModelArgs;
+use crate::{DeriveArgs, ModelArgs};
 use quote::{quote, ToTokens};
 use syn::GenericParam;
-use syn::{Attribute, Generics, ImplGenerics, ItemStruct, Path, Type, TypeGenerics};
+use syn::{
+    Attribute, Field, Generics, Ident, ImplGenerics, ItemStruct, LitStr, Path, Type, TypeGenerics,
+};
 
 const PROXY_MODEL_STRUCT_PATH: &str = "spark_orm::model::Model";
 const MODEL_TIMESTAMPS_TRAIT_PATH: &str = "spark_orm::model::util::ModelTimestamps";
+const MODEL_ID_TRAIT_PATH: &str = "spark_orm::model::util::ModelId";
 const MODEL_OBSERVER_TRAIT_PATH: &str = "spark_orm::model::observer::Observer";
+const MODEL_AFTER_LOAD_TRAIT_PATH: &str = "spark_orm::model::util::AfterLoad";
+const MODEL_SOFT_DELETE_TRAIT_PATH: &str = "spark_orm::model::util::SoftDelete";
+const MODEL_VALIDATE_TRAIT_PATH: &str = "spark_orm::model::validation::Validate";
+const INDEX_TYPE_ENUM_PATH: &str = "spark_orm::model::index::IndexType";
 
 pub fn generate(__struct: &ItemStruct, model_args: &ModelArgs) -> GeneratorResult<TokenStream> {
     let ident = &__struct.ident;
@@ -31,11 +38,28 @@ pub fn generate(__struct: &ItemStruct, model_args: &ModelArgs) -> GeneratorResul
     //this generates Observer<T> trait if user does' fill the observer
     let observer_trait = generate_observer_trait(__struct, model_args);
 
+    //this generates AfterLoad trait if user doesn't fill the after_load
+    let after_load_trait = generate_after_load_trait(__struct, model_args);
+
+    //this generates SoftDelete trait , it gives the deleted_at field if user fills the soft_delete
+    let deleted_name = __struct
+        .fields
+        .iter()
+        .find(|field| field.ident.as_ref().is_some_and(|ident| ident == "deleted_at"))
+        .map_or_else(|| "deleted_at".to_string(), stored_name);
+    let soft_delete_trait = generate_soft_delete_trait(__struct, model_args, &deleted_name);
+
+    //this generates Validate trait if user doesn't fill the validate
+    let validate_trait = generate_validate_trait(__struct, model_args);
+
     // this there lines first inspect that the user defined timestamp or not then create fields
     // for them and after that defines the update method for them
     let mut time_creator = vec![];
-    let filed_expand = generate_time_stamps(__struct, &mut time_creator);
-    let date_time_functions = generate_date_times_functions(__struct, time_creator);
+    let filed_expand = generate_time_stamps(__struct, model_args, &mut time_creator);
+    let date_time_functions = generate_date_times_functions(__struct, model_args, time_creator);
+
+    //this generates ModelId trait to read the _id without serializing the model
+    let model_id_trait = generate_model_id_trait(__struct, &quote::format_ident!("_id"));
 
     Ok(quote!(
         #struct_attrs
@@ -50,15 +74,146 @@ pub fn generate(__struct: &ItemStruct, model_args: &ModelArgs) -> GeneratorResul
 
         #date_time_functions
 
+        #model_id_trait
+
         #observer_trait
+
+        #after_load_trait
+
+        #soft_delete_trait
+
+        #validate_trait
     )
     .into())
 }
 
+/// this function generates the traits of `#[derive(RsparkModel)]` , it's like `generate` but the
+/// struct isn't changed , so the id and timestamp fields must be declared by the user
+pub fn generate_derive(__struct: &ItemStruct, args: &DeriveArgs) -> GeneratorResult<TokenStream> {
+    let id = required_field(__struct, args.id_field.as_deref().unwrap_or("_id"))?;
+    let created = required_field(__struct, args.created_field.as_deref().unwrap_or("created_at"))?;
+    let updated = required_field(__struct, args.updated_field.as_deref().unwrap_or("updated_at"))?;
+    let deleted_field = args.deleted_field.as_deref().unwrap_or("deleted_at");
+    let deleted = __struct
+        .fields
+        .iter()
+        .find(|field| field.ident.as_ref().is_some_and(|ident| ident == deleted_field));
+    if args.soft_delete.is_some() && deleted.is_none() {
+        return Err(syn::Error::new_spanned(
+            &__struct.ident,
+            format!("soft_delete needs the field {deleted_field}: Option<mongodb::bson::DateTime>"),
+        )
+        .into());
+    }
+    let deleted_name = deleted.map_or_else(|| deleted_field.to_string(), stored_name);
+
+    // the other generators read the same arguments as the Model attribute
+    let model_args = ModelArgs {
+        coll_name: args.coll_name.clone(),
+        observer: args.observer,
+        after_load: args.after_load,
+        soft_delete: args.soft_delete,
+        validate: args.validate,
+        created_field: Some(stored_name(created)),
+        updated_field: Some(stored_name(updated)),
+    };
+
+    let model_creator = generate_model_creator_impl(__struct, &model_args);
+    let from_to_document_trait = generate_from_to_document_trait(__struct);
+    let observer_trait = generate_observer_trait(__struct, &model_args);
+    let after_load_trait = generate_after_load_trait(__struct, &model_args);
+    let soft_delete_trait = generate_soft_delete_trait(__struct, &model_args, &deleted_name);
+    let validate_trait = generate_validate_trait(__struct, &model_args);
+    let model_id_trait = generate_model_id_trait(__struct, id.ident.as_ref().unwrap());
+
+    let model_name = &__struct.ident;
+    let (impl_generics, type_generics, where_generics) = prepare_generics(&__struct.generics);
+    let timestamps_trait = Path::from_string(MODEL_TIMESTAMPS_TRAIT_PATH).unwrap();
+    let created_name = stored_name(created);
+    let updated_name = stored_name(updated);
+    let created = &created.ident;
+    let updated = &updated.ident;
+    let deleted_at = match deleted.and_then(|field| field.ident.as_ref()) {
+        Some(deleted) => quote!(
+            fn deleted_at(&mut self, now: mongodb::bson::DateTime) {
+                self.#deleted = Some(now);
+            }
+        ),
+        None => quote!(),
+    };
+
+    Ok(quote!(
+        #model_creator
+
+        #from_to_document_trait
+
+        impl #impl_generics #timestamps_trait for #model_name #type_generics #where_generics {
+            const CREATED_FIELD: &'static str = #created_name;
+            const UPDATED_FIELD: &'static str = #updated_name;
+
+            fn created_at(&mut self, now: mongodb::bson::DateTime){
+                self.#created = Some(now);
+            }
+
+            fn updated_at(&mut self, now: mongodb::bson::DateTime){
+                self.#updated = Some(now);
+            }
+
+            #deleted_at
+        }
+
+        #model_id_trait
+
+        #observer_trait
+
+        #after_load_trait
+
+        #soft_delete_trait
+
+        #validate_trait
+    )
+    .into())
+}
+
+/// this function finds a field that `#[derive(RsparkModel)]` needs , it's an error if the user
+/// didn't declare it
+fn required_field<'s>(__struct: &'s ItemStruct, name: &str) -> GeneratorResult<&'s Field> {
+    __struct
+        .fields
+        .iter()
+        .find(|field| field.ident.as_ref().is_some_and(|ident| ident == name))
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &__struct.ident,
+                format!("RsparkModel needs the field {name} , declare it or use the Model attribute that adds it"),
+            )
+            .into()
+        })
+}
+
+/// this function returns the name that a field is stored with , it's the `serde(rename)` of
+/// the field if there is one
+fn stored_name(field: &Field) -> String {
+    let mut rename = None;
+    field.attrs.iter().filter(|attr| attr.path().is_ident("serde")).for_each(|attr| {
+        // a serde attribute that can't be parsed here just doesn't rename the field
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+    });
+    rename.unwrap_or_else(|| field.ident.as_ref().unwrap().to_string())
+}
+
 /// this function first checks that user defined create_at or ...
 /// then generates them if it isn't exist
 fn generate_time_stamps(
     __struct: &ItemStruct,
+    model_args: &ModelArgs,
     time_creator: &mut Vec<&str>,
 ) -> proc_macro2::TokenStream {
     let mut filed_expand = quote!();
@@ -71,21 +226,25 @@ fn generate_time_stamps(
     }
     if !check_filed_exists(__struct, "created_at") {
         time_creator.push("created_at");
+        let rename = field_rename(&model_args.created_field);
         filed_expand = quote!(
             #filed_expand
 
             #[serde(default = "Option::default")]
             #[serde(skip_serializing_if = "Option::is_none")]
+            #rename
             pub created_at: Option<mongodb::bson::DateTime>,
         )
     }
     if !check_filed_exists(__struct, "updated_at") {
         time_creator.push("updated_at");
+        let rename = field_rename(&model_args.updated_field);
         filed_expand = quote!(
             #filed_expand
 
             #[serde(default = "Option::default")]
             #[serde(skip_serializing_if = "Option::is_none")]
+            #rename
             pub updated_at: Option<mongodb::bson::DateTime>,
         )
     }
@@ -103,6 +262,14 @@ fn generate_time_stamps(
     filed_expand
 }
 
+/// this function renames a generated timestamp field if user gives another name for it
+fn field_rename(name: &Option<String>) -> proc_macro2::TokenStream {
+    match name {
+        Some(name) => quote!( #[serde(rename = #name)] ),
+        None => quote!(),
+    }
+}
+
 /// this function extracts user defined attributes for his struct
 fn extract_struct_attrs(__struct: &ItemStruct) -> proc_macro2::TokenStream {
     let mut struct_attrs = quote!();
@@ -130,7 +297,7 @@ fn regenerate_defined_filed(__struct: &ItemStruct) -> proc_macro2::TokenStream {
 
     let is_generic = |x: &Type| -> bool {
         gens.iter()
-            .any(|f| f.ident.to_string() == x.to_token_stream().to_string())
+            .any(|f| f.ident == x.to_token_stream().to_string())
     };
 
     //TODO clean this piece of code
@@ -166,7 +333,7 @@ fn regenerate_defined_filed(__struct: &ItemStruct) -> proc_macro2::TokenStream {
         let generic_att = if is_generic {
             let deserialize_string = format!(
                 "{} : spark_orm::DeserializeOwned",
-                filed_type.to_token_stream().to_string()
+                filed_type.to_token_stream()
             );
             quote!(  #[serde(bound(deserialize = #deserialize_string))] )
             // quote!(    #[serde(bound(deserialize = "T : serde::de::DeserializeOwned"))])
@@ -233,6 +400,12 @@ fn generate_model_creator_impl(
                     model
                 }
 
+                pub fn try_new_model<'a>(db: Option<& std::sync::Arc<mongodb::Database>>) -> spark_orm::error::SparkResult<#model<'a , Self>>{
+                    let model = #model::<Self>::try_new(db , #coll_name)?;
+                    Self::register_attributes(&model);
+                    Ok(model)
+                }
+
                 #register_attributes_function
             }
     }
@@ -241,37 +414,51 @@ fn generate_model_creator_impl(
 /// this function generates ModelTimestamp traits to update document times when needed
 fn generate_date_times_functions(
     __struct: &ItemStruct,
+    model_args: &ModelArgs,
     exists_fields: Vec<&str>,
 ) -> proc_macro2::TokenStream {
     let model_name = &__struct.ident;
     let (impl_generics, type_generics, where_generics) = prepare_generics(&__struct.generics);
     let tr = Path::from_string(MODEL_TIMESTAMPS_TRAIT_PATH).unwrap();
     let mut qu = quote!();
+    if let Some(name) = &model_args.created_field {
+        qu = quote! {
+            const CREATED_FIELD: &'static str = #name;
+        };
+    }
+    if let Some(name) = &model_args.updated_field {
+        qu = quote! {
+            #qu
+            const UPDATED_FIELD: &'static str = #name;
+        };
+    }
 
     //TODO the ModelTimestamps must split into 3 trait (CreatedAt , UpdatedAt , DeletedAt)
     // because now user cant change them for self
-    if exists_fields.iter().any(|x| *x == "created_at") {
+    if exists_fields.contains(&"created_at") {
         qu = quote! {
-                fn created_at(&mut self){
-                    self.created_at = Some(mongodb::bson::DateTime::now());
+            #qu
+
+                fn created_at(&mut self, now: mongodb::bson::DateTime){
+                    self.created_at = Some(now);
                 }
         };
     }
 
-    if exists_fields.iter().any(|x| *x == "deleted_at") {
+    if exists_fields.contains(&"updated_at") {
         qu = quote! {
               #qu
 
-                fn updated_at(&mut self){
-                    self.updated_at = Some(mongodb::bson::DateTime::now());
+                fn updated_at(&mut self, now: mongodb::bson::DateTime){
+                    self.updated_at = Some(now);
                 }
         };
     }
-    if exists_fields.iter().any(|x| *x == "deleted_at") {
+    if exists_fields.contains(&"deleted_at") {
         qu = quote! {
             #qu
-            fn deleted_at(&mut self){
-                self.deleted_at = Some(mongodb::bson::DateTime::now());
+            fn deleted_at(&mut self, now: mongodb::bson::DateTime){
+                self.deleted_at = Some(now);
             }
         };
     }
@@ -282,6 +469,23 @@ fn generate_date_times_functions(
     )
 }
 
+/// this function generates ModelId trait , the _id is either generated by generate_time_stamps
+/// or defined by user , so it always exists , `id` is the field that holds it
+fn generate_model_id_trait(__struct: &ItemStruct, id: &Ident) -> proc_macro2::TokenStream {
+    let model_name = &__struct.ident;
+    let (impl_generics, type_generics, where_generics) = prepare_generics(&__struct.generics);
+    let tr = Path::from_string(MODEL_ID_TRAIT_PATH).unwrap();
+    quote!(
+        impl #impl_generics #tr for #model_name #type_generics #where_generics {
+            fn model_id(&self) -> Option<mongodb::bson::Bson> {
+                mongodb::bson::to_bson(&self.#id)
+                    .ok()
+                    .filter(|id| *id != mongodb::bson::Bson::Null)
+            }
+        }
+    )
+}
+
 /// attr_to_compare must be without # and [] , like serde(default)
 fn attr_exists(attrs: &[Attribute], attr_to_compare: &str) -> bool {
     let mut has_it = false;
@@ -298,6 +502,7 @@ fn generate_register_attribute_function(__struct: &ItemStruct) -> proc_macro2::T
     let fields = &__struct.fields;
     let mut indexes = quote!();
     let model = Path::from_string(PROXY_MODEL_STRUCT_PATH).unwrap();
+    let index_type = Path::from_string(INDEX_TYPE_ENUM_PATH).unwrap();
 
     fields.iter().for_each(|field| {
         if attr_exists(&field.attrs, "index") {
@@ -305,7 +510,7 @@ fn generate_register_attribute_function(__struct: &ItemStruct) -> proc_macro2::T
             indexes = quote!(
                     #indexes
 
-                    #ident,
+                    (#ident, #index_type::Asc),
             );
         }
     });
@@ -315,10 +520,10 @@ fn generate_register_attribute_function(__struct: &ItemStruct) -> proc_macro2::T
         pub fn register_attributes<'a>(model: &#model<'a , Self>){
             let indexes = vec![#indexes];
             static registerer: std::sync::Once = std::sync::Once::new();
-            //TODO this must be fix
 
             registerer.call_once( ||{
-                model.register_attributes(indexes);
+                // new_model isn't async , so the indexes are registered in background
+                model.register_attributes_detached(indexes);
             });
         }
     )
@@ -338,14 +543,68 @@ fn generate_observer_trait(__struct: &ItemStruct, args: &ModelArgs) -> proc_macr
     quote!()
 }
 
+/// this function first checks that user wants to use after_load or not
+/// if user wants we don't generate trait unless we generate just trait with its default function
+fn generate_after_load_trait(__struct: &ItemStruct, args: &ModelArgs) -> proc_macro2::TokenStream {
+    if args.after_load.is_none() {
+        let after_load_trait = Path::from_string(MODEL_AFTER_LOAD_TRAIT_PATH).unwrap();
+        let model_name = &__struct.ident;
+        let (impl_generics, type_generics, where_generics) = prepare_generics(&__struct.generics);
+        return quote!(
+          impl #impl_generics #after_load_trait  for #model_name #type_generics #where_generics {}
+        );
+    }
+    quote!()
+}
+
+/// this function first checks that user wants to use validate or not
+/// if user wants we don't generate trait unless we generate just trait with its default function
+fn generate_validate_trait(__struct: &ItemStruct, args: &ModelArgs) -> proc_macro2::TokenStream {
+    if args.validate.is_none() {
+        let validate_trait = Path::from_string(MODEL_VALIDATE_TRAIT_PATH).unwrap();
+        let model_name = &__struct.ident;
+        let (impl_generics, type_generics, where_generics) = prepare_generics(&__struct.generics);
+        return quote!(
+          impl #impl_generics #validate_trait  for #model_name #type_generics #where_generics {}
+        );
+    }
+    quote!()
+}
+
+/// this function generates SoftDelete trait , the stored name of the deleted_at field is returned
+/// only if user fills the soft_delete , unless the trait is generated with its default function
+fn generate_soft_delete_trait(
+    __struct: &ItemStruct,
+    args: &ModelArgs,
+    deleted_field: &str,
+) -> proc_macro2::TokenStream {
+    let soft_delete_trait = Path::from_string(MODEL_SOFT_DELETE_TRAIT_PATH).unwrap();
+    let model_name = &__struct.ident;
+    let (impl_generics, type_generics, where_generics) = prepare_generics(&__struct.generics);
+    let field = if args.soft_delete.is_some() {
+        quote!(
+            fn deleted_at_field() -> Option<&'static str> {
+                Some(#deleted_field)
+            }
+        )
+    } else {
+        quote!()
+    };
+    quote!(
+      impl #impl_generics #soft_delete_trait  for #model_name #type_generics #where_generics {
+          #field
+      }
+    )
+}
+
 /// this function determines that the attribute a custom attribute means
 /// must remove it and replace it with something else
 fn is_custom_attribute(attr: &Attribute) -> bool {
     let custom_attributes = ["no_default", "index"];
 
-    return custom_attributes
+    custom_attributes
         .iter()
-        .any(|c| c == &attr.meta.to_token_stream().to_string());
+        .any(|c| c == &attr.meta.to_token_stream().to_string())
 }
 
 /// this function gets syn generics and add needed bounds
@@ -366,7 +625,7 @@ fn is_custom_attribute(attr: &Attribute) -> bool {
 /// ```
 /// the bounds are hard coded in NEEDED_BOUNDS variable
 ///
-fn prepare_generics(generics: &Generics) -> (ImplGenerics, TypeGenerics, proc_macro2::TokenStream) {
+fn prepare_generics(generics: &Generics) -> (ImplGenerics<'_>, TypeGenerics<'_>, proc_macro2::TokenStream) {
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     const NEEDED_BOUNDS: [&str; 7] = [
         "spark_orm::DeserializeOwned",
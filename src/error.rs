@@ -1,5 +1,12 @@
+use crate::model::validation::ValidationErrors;
+use mongodb::error::{ErrorKind as MongoErrorKind, WriteFailure};
 use thiserror::Error;
 
+/// the code of the server for a write that breaks a unique index
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+pub type SparkResult<T> = std::result::Result<T, SparkError>;
+
 #[derive(Debug, Error)]
 #[error("{}" , self.message)]
 pub struct Error {
@@ -9,6 +16,7 @@ pub struct Error {
 #[derive(Debug)]
 enum ErrorKind {
     TestKind,
+    NotFound,
 }
 
 impl Error {
@@ -18,6 +26,178 @@ impl Error {
             kind: ErrorKind::TestKind,
         }
     }
+
+    /// the error of a document that must exist but doesn't , it's `SparkError::NotFound`
+    /// after the conversion
+    pub(crate) fn not_found(message: &str) -> Error {
+        Error {
+            message: String::from(message),
+            kind: ErrorKind::NotFound,
+        }
+    }
+}
+
+/// The errors of Spark , the methods return `mongodb::error::Error` and `?` in a function
+/// that returns `SparkResult` converts it , the known failures become their own variant ,
+/// the `try_` writes like `Model::try_save` return `SparkResult` themselves
+///
+/// the conversion is the supported way to match the failures of the methods , the errors
+/// that the crate makes keep their variant and the driver errors are classified by their
+/// code , like 11000 for `Duplicate`
+/// ```ignore
+/// async fn register(user_model: &mut Model<'_, User>) -> SparkResult<()> {
+///     match user_model.try_save(None).await {
+///         Err(SparkError::Duplicate { key, .. }) => println!("{key} is taken"),
+///         other => {
+///             other?;
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Error)]
+pub enum SparkError {
+    /// a write broke a unique index , `key` is the duplicated key as the server reports it ,
+    /// like `{ email: "a@b.c" }`
+    #[error("The key {key} is duplicated")]
+    Duplicate {
+        key: String,
+        #[source]
+        source: mongodb::error::Error,
+    },
+    /// a document that must exist doesn't , like the document of a saved model that has an id
+    #[error("{0}")]
+    NotFound(String),
+    /// the model isn't valid , it's the error of `Validate::validate`
+    #[error("{0}")]
+    Validation(ValidationErrors),
+    /// the global connection is used before it's set
+    #[error("The global connection isn't initialized , call Spark::global_connect or SparkBuilder::build first")]
+    NotInitialized,
+    /// `Spark::transaction` is called inside the closure of another `Spark::transaction` ,
+    /// MongoDB has no nested transactions , use the session of the outer one instead
+    #[error("A transaction is already running in this task , use the session of the outer Spark::transaction")]
+    NestedTransaction,
+    /// any other error of the driver or the server
+    #[error(transparent)]
+    Mongo(mongodb::error::Error),
+}
+
+impl From<mongodb::error::Error> for SparkError {
+    fn from(value: mongodb::error::Error) -> Self {
+        if let Some(error) = value.get_custom::<SparkError>() {
+            return error.clone();
+        }
+        if let Some(errors) = value.get_custom::<ValidationErrors>() {
+            return SparkError::Validation(errors.clone());
+        }
+        if let Some(error) = value.get_custom::<Error>() {
+            if matches!(error.kind, ErrorKind::NotFound) {
+                return SparkError::NotFound(error.message.clone());
+            }
+        }
+        match duplicate_message(&value) {
+            Some(message) => SparkError::Duplicate {
+                key: duplicate_key(&message),
+                source: value,
+            },
+            None => SparkError::Mongo(value),
+        }
+    }
 }
 
+impl From<SparkError> for mongodb::error::Error {
+    fn from(value: SparkError) -> Self {
+        match value {
+            SparkError::Mongo(error)
+            | SparkError::Duplicate {
+                source: error,
+                ..
+            } => error,
+            SparkError::Validation(errors) => mongodb::error::Error::custom(errors),
+            SparkError::NotFound(message) => Error::not_found(&message).into(),
+            SparkError::NotInitialized | SparkError::NestedTransaction => {
+                mongodb::error::Error::custom(value)
+            }
+        }
+    }
+}
 
+/// the message of the first duplicate key failure of the error , a duplicate key can be
+/// reported by a write , by a write of a batch or by a command like findAndModify
+fn duplicate_message(error: &mongodb::error::Error) -> Option<String> {
+    match &*error.kind {
+        MongoErrorKind::Write(WriteFailure::WriteError(write_error))
+            if write_error.code == DUPLICATE_KEY_CODE =>
+        {
+            Some(write_error.message.clone())
+        }
+        MongoErrorKind::BulkWrite(failure) => failure
+            .write_errors
+            .iter()
+            .flatten()
+            .find(|write_error| write_error.code == DUPLICATE_KEY_CODE)
+            .map(|write_error| write_error.message.clone()),
+        MongoErrorKind::Command(command_error) if command_error.code == DUPLICATE_KEY_CODE => {
+            Some(command_error.message.clone())
+        }
+        _ => None,
+    }
+}
+
+/// reads the key of a message like `E11000 duplicate key error collection: db.users index: email_1
+/// dup key: { email: "a@b.c" }` , the whole message is the key if it has another format
+fn duplicate_key(message: &str) -> String {
+    match message.split_once("dup key: ") {
+        Some((_, key)) => key.trim().to_string(),
+        None => message.to_string(),
+    }
+}
+
+impl From<Error> for mongodb::error::Error {
+    fn from(value: Error) -> Self {
+        mongodb::error::Error::custom(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_key_is_read_from_the_message() {
+        let message = r#"E11000 duplicate key error collection: db.users index: email_1 dup key: { email: "a@b.c" }"#;
+        assert_eq!(duplicate_key(message), r#"{ email: "a@b.c" }"#);
+        assert_eq!(duplicate_key("duplicate"), "duplicate");
+    }
+
+    #[test]
+    fn errors_convert_both_ways() {
+        let not_found: mongodb::error::Error = Error::not_found("The document is gone").into();
+        // the existing callers still see the custom error of the crate
+        assert!(not_found.get_custom::<Error>().is_some());
+        assert!(
+            matches!(SparkError::from(not_found), SparkError::NotFound(message) if message == "The document is gone")
+        );
+
+        let mut errors = ValidationErrors::new();
+        errors.add("email", "The email is invalid");
+        let validation: mongodb::error::Error = SparkError::Validation(errors.clone()).into();
+        assert!(
+            matches!(SparkError::from(validation), SparkError::Validation(converted) if converted == errors)
+        );
+
+        let uninitialized: mongodb::error::Error = SparkError::NotInitialized.into();
+        assert!(matches!(SparkError::from(uninitialized), SparkError::NotInitialized));
+
+        // a variant stored as a custom error is returned as it is
+        let stored =
+            mongodb::error::Error::custom(SparkError::NotFound("The user is gone".to_string()));
+        assert!(
+            matches!(SparkError::from(stored), SparkError::NotFound(message) if message == "The user is gone")
+        );
+
+        let other: mongodb::error::Error = Error::new("Something else").into();
+        assert!(matches!(SparkError::from(other), SparkError::Mongo(_)));
+    }
+}
@@ -0,0 +1,73 @@
+use std::ops::Deref;
+
+use futures::AsyncRead;
+use mongodb::bson::oid::ObjectId;
+use mongodb::gridfs::{GridFsBucket, GridFsDownloadStream};
+use mongodb::options::{GridFsBucketOptions, GridFsUploadOptions};
+use mongodb::Database;
+
+/// A GridFS bucket for the files that are larger than the 16MB limit of a document , like avatars
+/// and exported PDFs , it uses the connection of its database so no other connection is opened
+///
+/// the methods of the driver bucket like `delete` and `find` are reachable through deref
+/// ```ignore
+/// let bucket = Spark::gridfs_bucket(Some("avatars"));
+/// let file_id = bucket.upload_stream("avatar.png", file, None).await?;
+/// user_model.avatar_id = Some(file_id);
+///
+/// let mut download = bucket.download_stream(file_id).await?;
+/// futures::io::copy(&mut download, &mut output).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bucket {
+    inner: GridFsBucket,
+}
+
+impl Bucket {
+    /// the bucket of the database , `None` name is the default `fs` bucket
+    pub fn new(db: &Database, name: Option<&str>) -> Self {
+        let options = GridFsBucketOptions::builder()
+            .bucket_name(name.map(str::to_string))
+            .build();
+        Bucket {
+            inner: db.gridfs_bucket(options),
+        }
+    }
+
+    /// stores the file that is read from `source` in chunks , so it isn't buffered in memory ,
+    /// returns the id of the stored file to keep it in a field of a model
+    pub async fn upload_stream<T>(
+        &self,
+        filename: &str,
+        source: T,
+        options: impl Into<Option<GridFsUploadOptions>>,
+    ) -> mongodb::error::Result<ObjectId>
+    where
+        T: AsyncRead + Unpin,
+    {
+        self.inner
+            .upload_from_futures_0_3_reader(filename, source, options)
+            .await
+    }
+
+    /// opens the file of the id , the chunks are read from the server while the stream is read
+    pub async fn download_stream(&self, id: ObjectId) -> mongodb::error::Result<GridFsDownloadStream> {
+        self.inner.open_download_stream(id.into()).await
+    }
+
+    /// like `download_stream` , it opens the latest uploaded file of the filename
+    pub async fn download_stream_by_name(
+        &self,
+        filename: &str,
+    ) -> mongodb::error::Result<GridFsDownloadStream> {
+        self.inner.open_download_stream_by_name(filename, None).await
+    }
+}
+
+impl Deref for Bucket {
+    type Target = GridFsBucket;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
@@ -4,6 +4,7 @@ extern crate self as spark_orm;
 
 mod connection;
 pub mod error;
+pub mod gridfs;
 pub mod model;
 pub mod types;
 pub mod client;
@@ -14,9 +15,9 @@ pub mod futures {
 }
 pub use preload::*;
 pub mod preload {
-    pub use spark_orm::error::Error;
+    pub use spark_orm::error::{Error, SparkError, SparkResult};
     pub use spark_orm::{Model};
-    pub use spark_orm::client::{Result, Spark};
+    pub use spark_orm::client::{Result, Spark, SparkBuilder};
     pub use serde::Serialize;
     pub use serde::Deserialize;
     pub use serde::de::DeserializeOwned;
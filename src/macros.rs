@@ -1,11 +1,11 @@
 macro_rules! trace {
     (target: $target:expr, $($arg:tt)+) => {
-        if cfg!(debug){
+        if cfg!(feature = "debug"){
             log::trace!(targjt: $target , $($arg)+);
         }
     };
     ($($arg:tt)+) => {
-        if cfg!(debug) {
+        if cfg!(feature = "debug") {
             log::trace!($($arg)+);
         }
     };
@@ -1,32 +1,88 @@
 #![allow(dead_code)]
 
+mod backup;
+pub mod batch;
+pub mod bulk;
+#[cfg(feature = "cache")]
+mod cache;
+pub mod index;
+mod instrument;
+pub mod migration;
 pub mod observer;
+mod populate;
+pub mod query;
+pub mod retry;
+pub mod schema;
+mod stream;
+pub mod tenant;
 pub mod util;
+pub mod validation;
 
-use crate::futures::StreamExt;
+use crate::error::{Error, SparkError, SparkResult};
+use crate::futures::{AsyncRead, AsyncWrite, AsyncWriteExt, Stream, StreamExt, TryStreamExt};
+use crate::gridfs::Bucket;
 use crate::macros::{error, trace};
-use crate::model::observer::Observer;
-use crate::model::util::ModelTimestamps;
+use crate::model::backup::{read_document, write_document};
+use crate::model::batch::{merge_result, offset_error, Batches, InsertBatching};
+use crate::model::bulk::{
+	check_update_operators, command_batching, merge_failure, reindex_error, response_count,
+	run_write_command, BulkWriteOptions, BulkWriteResult, WriteKind,
+};
+#[cfg(feature = "cache")]
+use crate::model::cache::QueryCache;
+use crate::model::index::{
+	case_insensitive, ensure_index, ensure_text_index, reconcile_indexes, IndexSpec, IndexType,
+};
+use crate::model::instrument::Operation;
+use crate::model::observer::{Observer, ObserverContext, WriteOp};
+use crate::model::populate::reference_ids;
+use crate::model::query::{projection, Query};
+use crate::model::retry::{
+	is_retryable_read, is_retryable_write, is_transient_write, Attempts, RetryPolicy,
+	SAVE_ATTEMPTS, SAVE_BACKOFF,
+};
+use crate::model::schema::SchemaReport;
+use crate::model::stream::resilient_stream;
+use crate::model::tenant::TenantScoped;
+use crate::model::util::{AfterLoad, ModelId, ModelTimestamps, SoftDelete};
+use crate::model::validation::{Validate, ValidationOrder};
+use crate::types::{ChangeEvent, Page, SaveOutcome, UpsertOutcome};
 use crate::Spark;
-use mongodb::bson::{doc, to_document, Document};
-use mongodb::error::Result;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::ser;
+use mongodb::bson::{
+	doc, from_bson, from_document, to_bson, to_document, to_raw_document_buf, Bson, DateTime,
+	Document, RawDocumentBuf,
+};
+use mongodb::error::{ErrorKind, Result};
 use mongodb::options::{
-	DeleteOptions, DropIndexOptions, FindOneOptions, FindOptions, InsertOneOptions,
-	ListIndexesOptions, UpdateOptions,
+	AggregateOptions, ChangeStreamOptions, CollectionOptions, CountOptions,
+	CreateCollectionOptions, DeleteOptions, DistinctOptions, FindOneAndDeleteOptions,
+	FindOneAndUpdateOptions, FindOneOptions, FindOptions, InsertManyOptions, InsertOneOptions,
+	ReadPreference, ReturnDocument, SelectionCriteria, TimeseriesGranularity, TimeseriesOptions,
+	UpdateOptions, WriteConcern,
 };
-use mongodb::results::UpdateResult;
-use mongodb::{Collection, Cursor, Database, IndexModel};
+use mongodb::results::{CollectionType, InsertManyResult, UpdateResult};
+use mongodb::{ClientSession, Collection, Cursor, Database};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::future::Future;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::task::JoinHandle;
 
 // TODO: this must move to types module
 type Id = mongodb::bson::Bson;
 pub type MongodbResult<T> = Result<T>;
 
+/// the field that `search_scored` projects the relevance into , it's removed before the model is loaded
+const TEXT_SCORE_FIELD: &str = "_text_score";
+
+/// the models bigger than this many bytes are boxed by `Model::new`
 const HEAP_THRESHOLD: usize = 256;
 
 #[derive(Serialize, Debug)]
@@ -35,6 +91,33 @@ pub enum Inner<M> {
 	Heap(Box<M>),
 }
 
+/// what a write of `save` did
+#[derive(Debug)]
+enum Saved {
+	Created(Id),
+	Updated(Id, Box<ObserverContext>),
+}
+
+impl Saved {
+	fn outcome(&self) -> SaveOutcome {
+		match self {
+			Saved::Created(id) => SaveOutcome::Created(id.clone()),
+			Saved::Updated(id, _) => SaveOutcome::Updated(id.clone()),
+		}
+	}
+}
+
+/// a write with a session whose observers wait for the commit
+#[derive(Debug)]
+enum Pending {
+	/// the global observer is notified and the cache is cleared
+	Written(WriteOp, Box<Document>),
+	/// only the cache is cleared , the write has no global notification like `update`
+	Invalidated,
+	Saved(Saved),
+	Deleted(Box<ObserverContext>),
+}
+
 #[derive(Debug, Serialize)]
 pub struct Model<'a, M> {
 	inner: Inner<M>,
@@ -44,6 +127,38 @@ pub struct Model<'a, M> {
 	collection_name: &'a str,
 	#[serde(skip)]
 	collection: Collection<M>,
+	#[serde(skip)]
+	scope: Option<Document>,
+	#[serde(skip)]
+	batching: InsertBatching,
+	#[serde(skip)]
+	bulk_observers: bool,
+	#[serde(skip)]
+	with_trashed: bool,
+	#[serde(skip)]
+	pending: Vec<Pending>,
+	#[serde(skip)]
+	retry: Option<RetryPolicy>,
+	#[serde(skip)]
+	validation_order: ValidationOrder,
+	#[cfg(feature = "cache")]
+	#[serde(skip)]
+	cache: Option<Arc<QueryCache>>,
+}
+
+impl<M> Inner<M> {
+	fn new(inner: M) -> Self {
+		if std::mem::size_of::<M>() > HEAP_THRESHOLD {
+			Inner::Heap(Box::new(inner))
+		} else {
+			Inner::Stack(inner)
+		}
+	}
+
+	#[cfg(test)]
+	fn is_heap(&self) -> bool {
+		matches!(self, Inner::Heap(_))
+	}
 }
 
 impl<M> Deref for Inner<M> {
@@ -89,7 +204,11 @@ where
 	M: Unpin,
 	M: Debug,
 	M: ModelTimestamps,
+	M: ModelId,
 	M: Observer<M>,
+	M: AfterLoad,
+	M: SoftDelete,
+	M: Validate,
 {
 	/// makes a model and stores the data and collection_name to creating collection object
 	/// to store data into it
@@ -103,7 +222,7 @@ where
 	///
 	/// # Examples
 	///
-	/// ```
+	/// ```ignore
 	/// struct User{
 	///     name: String
 	/// }
@@ -111,79 +230,639 @@ where
 	/// let user_model = Model::<User>::new(Arc::clone(db) , "users");
 	/// ```
 	pub fn new(db: Option<&Arc<Database>>, collection_name: &'a str) -> Model<'a, M> {
-		let inner = if std::mem::size_of::<M>() > 250 {
-			Inner::Heap(Box::<M>::default())
-		} else {
-			Inner::Stack(M::default())
-		};
-
-		if let Some(database) = db {
-			let collection = database.collection::<M>(collection_name);
-			return Model {
-				inner,
-				// inner: Box::<M>::default(),
-				db: database.clone(),
-				collection_name,
-				collection,
-			};
+		match Self::try_new(db, collection_name) {
+			Ok(model) => model,
+			// it panics if it's not initialized before use
+			Err(error) => panic!("{error}"),
 		}
-		// it panics if it's not initialized before use
-		let database = Spark::get_db();
+	}
+
+	/// like `new` but it returns `SparkError::NotInitialized` instead of panicking when `db` is
+	/// None and the global connection isn't initialized
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let user_model = Model::<User>::try_new(None, "users")?;
+	/// ```
+	pub fn try_new(
+		db: Option<&Arc<Database>>,
+		collection_name: &'a str,
+	) -> SparkResult<Model<'a, M>> {
+		let database = match db {
+			Some(database) => database.clone(),
+			None => Spark::try_get_db().ok_or(SparkError::NotInitialized)?,
+		};
 		let collection = database.collection::<M>(collection_name);
-		Model {
-			inner,
+		Ok(Model {
+			inner: Inner::new(M::default()),
 			db: database,
 			collection_name,
 			collection,
-		}
+			scope: None,
+			batching: InsertBatching::default(),
+			bulk_observers: true,
+			with_trashed: false,
+			pending: Vec::new(),
+			retry: None,
+			validation_order: ValidationOrder::default(),
+			#[cfg(feature = "cache")]
+			cache: None,
+		})
+	}
+
+	/// scopes the model to a tenant , after this every find , update and delete filter
+	/// gets `{ tenant_field: tenant_id }` so a query can't reach the documents of other tenants
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let user_model = User::new_model(None).scoped(Bson::from("tenant_42"));
+	/// let users = user_model.find_and_collect(doc! {}, None).await?;
+	/// ```
+	pub fn scoped(mut self, tenant_id: Bson) -> Self
+	where
+		M: TenantScoped,
+	{
+		self.scope = Some(doc! {
+			M::tenant_field(): tenant_id
+		});
+		self
+	}
+
+	/// makes the reads of a soft deleting model include the soft deleted documents
+	pub fn with_trashed(mut self) -> Self {
+		self.with_trashed = true;
+		self
+	}
+
+	/// makes the writes of the model like `save` and `update` use the write concern ,
+	/// ex : `WriteConcern::MAJORITY` for the critical writes
+	///
+	/// the collection of the model is derived again , the database and the other models keep
+	/// their own concerns
+	pub fn with_write_concern(self, write_concern: WriteConcern) -> Self {
+		self.with_collection_options(|options| options.write_concern = Some(write_concern))
+	}
+
+	/// makes the reads of the model like `find_one` and `find_and_collect` use the read
+	/// preference , ex : `ReadPreference::Secondary` for the analytics queries
+	///
+	/// the collection of the model is derived again , the database and the other models keep
+	/// their own read preference
+	pub fn with_read_preference(self, read_preference: ReadPreference) -> Self {
+		self.with_collection_options(|options| {
+			options.selection_criteria = Some(SelectionCriteria::ReadPreference(read_preference))
+		})
+	}
+
+	/// makes the reads like `find` , `find_one` , `count` , `paginate` and `aggregate` and the
+	/// writes like `save` , `upsert` , `update` , `patch` , `find_one_and_update` , `delete` and
+	/// `delete_many` run again by the policy when they fail with a retryable error , like a
+	/// primary stepdown or a network error , the other errors like duplicate key or validation
+	/// are returned right away
+	///
+	/// the observers are dispatched once , the retries just repeat the database operation ,
+	/// an insert whose first try reached the server before the network error can be applied twice ,
+	/// set the `_id` before `save` to make the second try fail with duplicate key instead
+	pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+		self.retry = Some(policy);
+		self
+	}
+
+	/// sets when `save` validates the model , `ValidationOrder::BeforeHooks` is the default , use
+	/// `ValidationOrder::AfterHooks` when `creating` or `updating` set values that must be valid
+	pub fn with_validation_order(mut self, order: ValidationOrder) -> Self {
+		self.validation_order = order;
+		self
+	}
+
+	/// makes `find_one` , `fetch` and `find_and_collect` remember their results for the ttl ,
+	/// the same read with the same filter and options is answered from memory , it's for the
+	/// collections that rarely change like the country codes
+	///
+	/// every write of this model or of the models made from it , like `save` , `update` and
+	/// `delete` , forgets all of the remembered reads , the cache is kept in this process only ,
+	/// so the writes of other processes or other models of the collection aren't seen until the
+	/// ttl passes
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// // kept like in the state of the app , a new model has an empty cache
+	/// let countries = Country::new_model(None).with_cache(Duration::from_secs(300));
+	/// let all = countries.find_and_collect(doc! {}, None).await?;
+	/// ```
+	#[cfg(feature = "cache")]
+	pub fn with_cache(mut self, ttl: Duration) -> Self {
+		self.cache = Some(Arc::new(QueryCache::new(ttl)));
+		self
 	}
 
 	/// saves the change , if the inner has some _id then it's update the existing unless
-	/// it's create  new document
+	/// it's create  new document , when the _id matches no document it returns an error
+	/// instead of inserting a new one
+	///
+	/// the steps of save always run in this order
+	/// 1. `Validate::validate`
+	/// 2. the `saving` observer , it can set the _id that decides the next step
+	/// 3. the `creating` or `updating` observer , its changes to the model are saved
+	/// 4. the timestamps
+	/// 5. serializing the model
+	/// 6. the write to database and the global observer
+	/// 7. the `created` or `updated` observer
+	///
+	/// with `ValidationOrder::AfterHooks` of `with_validation_order` the validation is the step
+	/// after `creating` or `updating` instead , an invalid model or an error of `saving` ,
+	/// `creating` or `updating` aborts the save before anything is written
+	///
+	/// it returns `SaveOutcome::Created` or `SaveOutcome::Updated` with the _id , an update whose
+	/// document already had the same values is still `Updated` and dispatches `updated`
 	pub async fn save(
 		&mut self,
 		options: impl Into<Option<InsertOneOptions>>,
-	) -> MongodbResult<Id> {
-		self.inner.updated_at();
-		let mut converted = to_document(&self.inner)?;
-		if let Some(id) = converted.get("_id") {
-			let owned_id = id.to_owned();
-			let upsert = self
-				.collection
-				.update_one(
-					doc! {
-						"_id" : id
-					},
-					doc! { "$set": &converted},
-					None,
-				)
-				.await?;
-			if upsert.modified_count >= 1 {
-				// dispatch call
+	) -> MongodbResult<SaveOutcome> {
+		let options = options.into();
+		let id = self.before_save().await?;
+		// the write needs the model mutably , so it can't be a closure of `retrying`
+		let mut attempts = Attempts::new(self.retry, self.collection_name, "save");
+		let saved = loop {
+			let operation = Operation::start(self.collection_name, "save", None);
+			match operation.run(self.write(id.clone(), options.clone(), None)).await {
+				Err(error) if attempts.retry(&error, is_retryable_write).await => {}
+				result => break result?,
+			}
+		};
+		self.dispatch_saved(saved).await
+	}
+
+	/// like `save` but the error is a `SparkError` , like `SparkError::Duplicate` for a taken
+	/// unique key or `SparkError::Validation` for an invalid model
+	pub async fn try_save(
+		&mut self,
+		options: impl Into<Option<InsertOneOptions>>,
+	) -> SparkResult<SaveOutcome> {
+		Ok(self.save(options).await?)
+	}
+
+	/// like `save` but the write uses the session , like the session of `Spark::transaction` ,
+	/// the `created` or `updated` observer waits for the commit and runs by `dispatch_committed`
+	/// because the write isn't visible before it
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// // the models are moved into the closure and returned , the future can't borrow them
+	/// let mut order_model = Spark::transaction(move |session| {
+	///     async move {
+	///         order_model.save_with_session(session, None).await?;
+	///         product_model
+	///             .update_with_session(doc! { "_id": product_id }, doc! { "$inc": { "stock": -1 } }, None, session)
+	///             .await?;
+	///         Ok(order_model)
+	///     }
+	///     .boxed()
+	/// })
+	/// .await?;
+	/// order_model.dispatch_committed().await?;
+	/// ```
+	pub async fn save_with_session(
+		&mut self,
+		session: &mut ClientSession,
+		options: impl Into<Option<InsertOneOptions>>,
+	) -> MongodbResult<SaveOutcome> {
+		let id = self.before_save().await?;
+		let saved = self.write(id, options.into(), Some(session)).await?;
+		let outcome = saved.outcome();
+		self.pending.push(Pending::Saved(saved));
+		Ok(outcome)
+	}
+
+	/// dispatches the observers of the writes with a session in their order , call it after
+	/// `Spark::transaction` succeeded , the writes of an aborted transaction must be dropped by
+	/// `discard_uncommitted` instead
+	pub async fn dispatch_committed(&mut self) -> MongodbResult<()> {
+		for pending in std::mem::take(&mut self.pending) {
+			match pending {
+				Pending::Written(op, doc) => self.notify_write(op, &doc),
+				Pending::Invalidated => self.invalidate_cache(),
+				Pending::Saved(saved) => {
+					self.dispatch_saved(saved).await?;
+				}
 				// this must be pinned to handle recursive async call
-				Box::pin(M::updated(self)).await?;
+				Pending::Deleted(context) => {
+					Box::pin(M::deleted_with_context(self, &context)).await?
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// drops the observers of the writes with a session , like the writes of an aborted transaction
+	pub fn discard_uncommitted(&mut self) {
+		self.pending.clear();
+	}
+
+	/// like `save` but converts the `_id` of the saved document into `I` , like an `ObjectId`
+	/// or a `String` , it returns an error if the `_id` can't be converted
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let id: ObjectId = user_model.save_typed().await?;
+	/// ```
+	pub async fn save_typed<I: DeserializeOwned>(&mut self) -> MongodbResult<I> {
+		let id = self.save(None).await?.into_id();
+		from_bson(id.clone()).map_err(|error| {
+			Error::new(&format!(
+				"The saved _id {id} of {} can't be converted : {error}",
+				self.collection_name
+			))
+			.into()
+		})
+	}
+
+	/// writes the model to the first document of the query or inserts it when nothing matches ,
+	/// unlike `save` the document is found by the query , like a natural key as `email`
+	///
+	/// `created_at` is set only when the document is inserted and `updated_at` is always set ,
+	/// the `_id` of an inserted document is the `_id` of the model or a new ObjectId , then the
+	/// `created` or `updated` observer is dispatched like `save` , the model is validated first
+	///
+	/// the `upsert` , `return_document` and `projection` of the options are overridden
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// user_model.email = "john@mail.com".to_string();
+	/// user_model.name = "John".to_string();
+	/// let outcome = user_model.upsert(doc! { "email": "john@mail.com" }, None).await?;
+	/// ```
+	pub async fn upsert(
+		&mut self,
+		query: impl Into<Document>,
+		options: impl Into<Option<FindOneAndUpdateOptions>>,
+	) -> MongodbResult<UpsertOutcome> {
+		self.inner.validate()?;
+		let now = DateTime::now();
+		let query = self.scope_filter(query.into());
+		let mut set = to_document(&*self.inner)?;
+		let model_id = set.remove("_id").filter(|id| *id != Bson::Null);
+		set.remove(M::CREATED_FIELD);
+		set.insert(M::UPDATED_FIELD, now);
+
+		let mut set_on_insert = doc! { M::CREATED_FIELD: now };
+		// the server copies an _id of the query into the inserted document
+		let id = match query.get("_id") {
+			Some(id) => id.clone(),
+			None => {
+				let id = model_id.unwrap_or_else(|| Bson::ObjectId(ObjectId::new()));
+				set_on_insert.insert("_id", id.clone());
+				id
+			}
+		};
+		let update = doc! { "$set": &set, "$setOnInsert": set_on_insert };
+
+		let mut options = options.into().unwrap_or_default();
+		options.upsert = Some(true);
+		// the document before the update tells that it's updated and gives its _id
+		options.return_document = Some(ReturnDocument::Before);
+		// and the values of the set fields before the update for the observers
+		let mut projection =
+			set.keys().map(|key| (key.clone(), Bson::Int32(1))).collect::<Document>();
+		projection.insert("_id", 1);
+		options.projection = Some(projection);
+
+		let collection = self.collection.clone_with_type::<Document>();
+		let before = self
+			.retrying("upsert", Some(&query), is_retryable_write, || {
+				collection.find_one_and_update(query.clone(), update.clone(), options.clone())
+			})
+			.await?;
+
+		let (outcome, previous) = match before {
+			Some(mut before) => {
+				self.inner.updated_at(now);
+				let existing = before.remove("_id").unwrap_or(id);
+				(UpsertOutcome::Updated(existing), Some(before))
+			}
+			None => {
+				self.inner.created_at(now);
+				self.inner.updated_at(now);
+				(UpsertOutcome::Inserted(id), None)
+			}
+		};
 
-				return Ok(owned_id);
+		self.invalidate_cache();
+		if let Some(observer) = Spark::global_observer() {
+			let op = if outcome.is_inserted() {
+				WriteOp::Insert
+			} else {
+				WriteOp::Update
 			};
+			observer.on_write(self.collection_name, op, &set);
+		}
+		// this must be pinned to handle recursive async call
+		match &outcome {
+			UpsertOutcome::Inserted(id) => {
+				let context = ObserverContext::created(id.clone());
+				Box::pin(M::created_with_context(self, &context)).await?
+			}
+			UpsertOutcome::Updated(id) => {
+				let context = ObserverContext::updated(Some(id.clone()), set, previous);
+				Box::pin(M::updated_with_context(self, &context)).await?
+			}
 		}
-		converted.remove("_id");
-		self.inner.created_at();
+		Ok(outcome)
+	}
 
-		let re = self.collection.insert_one(&*self.inner, options).await?;
+	/// like `upsert` but the error is a `SparkError`
+	pub async fn try_upsert(
+		&mut self,
+		query: impl Into<Document>,
+		options: impl Into<Option<FindOneAndUpdateOptions>>,
+	) -> SparkResult<UpsertOutcome> {
+		Ok(self.upsert(query, options).await?)
+	}
 
-		// dispatch observer
+	/// like `save` but retries the write with a small backoff when it fails because of a
+	/// transient write conflict , other errors like duplicate key are returned right away
+	///
+	/// the observers are dispatched once , the retries just repeat the write
+	pub async fn save_retrying(
+		&mut self,
+		options: impl Into<Option<InsertOneOptions>>,
+	) -> MongodbResult<SaveOutcome> {
+		let options = options.into();
+		let id = self.before_save().await?;
+		let mut backoff = SAVE_BACKOFF;
+		let mut attempt = 1;
+		let saved = loop {
+			match self.write(id.clone(), options.clone(), None).await {
+				Err(error) if attempt < SAVE_ATTEMPTS && is_transient_write(&error) => {
+					trace!("Retry save on {} after write conflict {error}", self.collection_name);
+					tokio::time::sleep(backoff).await;
+					backoff *= 2;
+					attempt += 1;
+				}
+				result => break result?,
+			}
+		};
+		self.dispatch_saved(saved).await
+	}
+
+	/// validates the model and dispatches `saving` , decides that save updates or inserts then
+	/// dispatches the observer that runs before the write , returns the _id of the document to update
+	async fn before_save(&mut self) -> MongodbResult<Option<Id>> {
+		if self.validation_order == ValidationOrder::BeforeHooks {
+			self.inner.validate()?;
+		}
 		// this must be pinned to handle recursive async call
-		Box::pin(M::created(self)).await?;
+		Box::pin(M::saving(self)).await?;
+		let id = self.inner.model_id();
+		match id {
+			Some(_) => Box::pin(M::updating(self)).await?,
+			None => Box::pin(M::creating(self)).await?,
+		}
+		if self.validation_order == ValidationOrder::AfterHooks {
+			// the model is validated as it's written , after the changes of the observers
+			self.inner.validate()?;
+		}
+		Ok(id)
+	}
+
+	/// writes the inner to database , it updates the document of the id if there is
+	/// unless it inserts a new one , an id that matches no document is an error
+	async fn write(
+		&mut self,
+		id: Option<Id>,
+		options: Option<InsertOneOptions>,
+		mut session: Option<&mut ClientSession>,
+	) -> MongodbResult<Saved> {
+		let now = DateTime::now();
+		if let Some(id) = id {
+			self.inner.updated_at(now);
+			// the inner is serialized once , the same document is the `$set` and goes to the observers
+			let converted = to_document(&*self.inner)?;
+			let filter = self.scope_filter(doc! {
+				"_id" : &id
+			});
+			let update = doc! { "$set": &converted};
+			// the same write returns the values of the set fields before it for the observers
+			let projection = converted.keys().map(|key| (key.clone(), Bson::Int32(1))).collect();
+			let options = FindOneAndUpdateOptions::builder()
+				.return_document(ReturnDocument::Before)
+				.projection(Some(projection))
+				.build();
+			let collection = self.collection.clone_with_type::<Document>();
+			let previous = match session.as_deref_mut() {
+				Some(session) => {
+					collection
+						.find_one_and_update_with_session(filter, update, options, session)
+						.await?
+				}
+				None => collection.find_one_and_update(filter, update, options).await?,
+			};
+			// a document whose values are the same is matched but not modified , it's still updated
+			let Some(previous) = previous else {
+				return Err(Error::not_found(&format!(
+					"The document {id} doesn't exist in {} , it can't be updated",
+					self.collection_name
+				))
+				.into());
+			};
+			// a write with a session is visible after the commit , so it's notified then
+			if session.is_some() {
+				let written = Box::new(converted.clone());
+				self.pending.push(Pending::Written(WriteOp::Update, written));
+			} else {
+				self.notify_write(WriteOp::Update, &converted);
+			}
+
+			let context = ObserverContext::updated(Some(id.clone()), converted, Some(previous));
+			return Ok(Saved::Updated(id, Box::new(context)));
+		}
+		// a new document is created and updated at the same time
+		self.inner.created_at(now);
+		self.inner.updated_at(now);
+
+		// the inner is serialized once , the same document is inserted and goes to the observers
+		let mut created = to_document(&*self.inner)?;
+		// a null _id of a model that declares its own _id is made by the server
+		if created.get("_id") == Some(&Bson::Null) {
+			created.remove("_id");
+		}
+		let collection = self.collection.clone_with_type::<Document>();
+		let with_session = session.is_some();
+		let re = match session {
+			Some(session) => collection.insert_one_with_session(&created, options, session).await?,
+			None => collection.insert_one(&created, options).await?,
+		};
 
-		Ok(re.inserted_id)
+		created.insert("_id", re.inserted_id.clone());
+		if with_session {
+			self.pending.push(Pending::Written(WriteOp::Insert, Box::new(created)));
+		} else {
+			self.notify_write(WriteOp::Insert, &created);
+		}
+
+		Ok(Saved::Created(re.inserted_id))
+	}
+
+	/// dispatches the observer of a write and returns what the write did
+	async fn dispatch_saved(&mut self, saved: Saved) -> MongodbResult<SaveOutcome> {
+		let outcome = saved.outcome();
+		// this must be pinned to handle recursive async call
+		match saved {
+			Saved::Updated(_, context) => {
+				Box::pin(M::updated_with_context(self, &context)).await?;
+			}
+			Saved::Created(id) => {
+				let context = ObserverContext::created(id);
+				Box::pin(M::created_with_context(self, &context)).await?;
+			}
+		}
+		Ok(outcome)
 	}
+
+	/// finds one document and fills the model with it , `AfterLoad::after_load` is called
+	/// on the loaded document
+	///
+	/// it's the same as `load` , use `fetch` to keep the model untouched
 	pub async fn find_one(
 		&mut self,
 		doc: impl Into<Document>,
 		options: impl Into<Option<FindOneOptions>>,
 	) -> MongodbResult<Option<&mut Self>> {
-		let result = self.collection.find_one(Some(doc.into()), options).await?;
-		match result {
+		self.load(doc, options).await
+	}
+
+	/// finds the document of the `_id` and fills the model with it like `find_one`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let mut user_model = User::new_model(None);
+	/// user_model.find_by_id(id, None).await?;
+	/// ```
+	pub async fn find_by_id(
+		&mut self,
+		id: impl Into<Bson>,
+		options: impl Into<Option<FindOneOptions>>,
+	) -> MongodbResult<Option<&mut Self>> {
+		self.find_one(doc! { "_id": id.into() }, options).await
+	}
+
+	/// like `find_by_id` but parses the hex string of an `ObjectId` , a malformed id is an error
+	pub async fn find_by_id_str(
+		&mut self,
+		id: &str,
+		options: impl Into<Option<FindOneOptions>>,
+	) -> MongodbResult<Option<&mut Self>> {
+		let id = ObjectId::parse_str(id)
+			.map_err(|error| Error::new(&format!("The id {id} isn't an ObjectId : {error}")))?;
+		self.find_by_id(id, options).await
+	}
+
+	/// reloads the model from its document , so the changes of another process are seen
+	/// before the next `save` , it returns false and leaves the model untouched when the
+	/// document doesn't exist anymore
+	///
+	/// the model must have an `_id` , a model that isn't saved yet is an error
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// if user_model.refresh().await? {
+	///     user_model.age += 1;
+	///     user_model.save(None).await?;
+	/// }
+	/// ```
+	pub async fn refresh(&mut self) -> MongodbResult<bool> {
+		let Some(id) = self.inner.model_id() else {
+			return Err(Error::new(&format!(
+				"The model of {} has no _id , only a saved model can be refreshed",
+				self.collection_name
+			))
+			.into());
+		};
+		Ok(self.find_by_id(id, None).await?.is_some())
+	}
+
+	/// finds one document and returns it without touching the model ,
+	/// `AfterLoad::after_load` is called on the returned document
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let user_model = User::new_model(None);
+	/// let user: Option<User> = user_model.fetch(doc! { "name": "Hossein" }, None).await?;
+	/// ```
+	pub async fn fetch(
+		&self,
+		doc: impl Into<Document>,
+		options: impl Into<Option<FindOneOptions>>,
+	) -> MongodbResult<Option<M>> {
+		let filter = self.read_filter(doc.into());
+		let options = options.into();
+		#[cfg(feature = "cache")]
+		if let Some(cache) = &self.cache {
+			let key = QueryCache::key("find_one", &filter, &options);
+			let documents = match cache.get(&key) {
+				Some(documents) => {
+					Operation::cache_hit(self.collection_name, "find_one", Some(&filter));
+					documents
+				}
+				None => {
+					let collection = self.collection.clone_with_type::<Document>();
+					let found = self
+						.retrying("find_one", Some(&filter), is_retryable_read, || {
+							collection.find_one(Some(filter.clone()), options.clone())
+						})
+						.await?;
+					let documents = Vec::from_iter(found);
+					cache.insert(key, documents.clone());
+					documents
+				}
+			};
+			return documents.into_iter().next().map(Self::loaded).transpose();
+		}
+		let result = self
+			.retrying("find_one", Some(&filter), is_retryable_read, || {
+				self.collection.find_one(Some(filter.clone()), options.clone())
+			})
+			.await?;
+		Ok(result.map(|mut inner| {
+			inner.after_load();
+			inner
+		}))
+	}
+
+	/// like `fetch` but the read uses the session , like the snapshot session of `Spark::read_snapshot`
+	pub async fn fetch_with_session(
+		&self,
+		doc: impl Into<Document>,
+		options: impl Into<Option<FindOneOptions>>,
+		session: &mut ClientSession,
+	) -> MongodbResult<Option<M>> {
+		let result = self
+			.collection
+			.find_one_with_session(Some(self.read_filter(doc.into())), options, session)
+			.await?;
+		Ok(result.map(|mut inner| {
+			inner.after_load();
+			inner
+		}))
+	}
+
+	/// finds one document and replaces the inner of the model with it , the model is left
+	/// untouched when nothing matches
+	pub async fn load(
+		&mut self,
+		doc: impl Into<Document>,
+		options: impl Into<Option<FindOneOptions>>,
+	) -> MongodbResult<Option<&mut Self>> {
+		match self.fetch(doc, options).await? {
 			Some(inner) => {
 				self.fill(inner);
 				Ok(Some(self))
@@ -192,10 +871,49 @@ where
 		}
 	}
 
+	/// like `find_one` but the matched documents are ordered by `sort` first , so when many
+	/// documents match the same one is returned every time , the `sort` overrides the sort of `options`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// // the most recent user named Hossein
+	/// let mut user_model = User::new_model(None);
+	/// user_model.find_one_sorted(doc! { "name": "Hossein" }, doc! { "created_at": -1 }, None).await?;
+	/// ```
+	pub async fn find_one_sorted(
+		&mut self,
+		doc: impl Into<Document>,
+		sort: impl Into<Document>,
+		options: impl Into<Option<FindOneOptions>>,
+	) -> MongodbResult<Option<&mut Self>> {
+		let mut options = options.into().unwrap_or_default();
+		options.sort = Some(sort.into());
+		self.find_one(doc, options).await
+	}
+
+	/// fills the model with the newest document of the filter by its created timestamp , an empty
+	/// filter takes the whole collection , the `_id` orders the documents that are created at the
+	/// same millisecond
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// user_model.latest(doc! { "name": "Hossein" }).await?;
+	/// ```
+	pub async fn latest(&mut self, doc: impl Into<Document>) -> MongodbResult<Option<&mut Self>> {
+		self.find_one_sorted(doc, doc! { M::CREATED_FIELD: -1, "_id": -1 }, None).await
+	}
+
+	/// like `latest` but fills the model with the oldest document of the filter
+	pub async fn oldest(&mut self, doc: impl Into<Document>) -> MongodbResult<Option<&mut Self>> {
+		self.find_one_sorted(doc, doc! { M::CREATED_FIELD: 1, "_id": 1 }, None).await
+	}
+
 	/// this is raw update , and you can pass document or your model
 	/// # Examples
 	/// ## with the raw doc
-	///  ```
+	///  ```ignore
 	///  let user_model = User::new_model(Some(&db));
 	///     let updated = user_model.update(
 	///         doc! {
@@ -210,6 +928,7 @@ where
 	///     ).await.unwrap();
 	/// ```
 	/// ## with the model
+	/// ```ignore
 	/// let user_model = User::new_model(Some(&db));
 	///     let mut sample_user = User::default();
 	///     sample_user.name = "Hossein 33".to_string();
@@ -222,13 +941,15 @@ where
 	///        },
 	///        None,
 	///    ).await.unwrap();
+	/// ```
 	///
 	/// ## with_model_instance
+	/// ```ignore
 	///     let mut user_model = User::new_model(Some(&db));
 	///    user_model.name = "Hossein 3355".to_string();
 	///    user_model.age = 58;
 	///    let updated = user_model.update(
-	///        &user_model,
+	///        Document::try_from(&user_model)?,
 	///        doc! {
 	///            "$set": {
 	///                "name": "Hossein 325"
@@ -236,122 +957,1528 @@ where
 	///        },
 	///        None,
 	///    ).await.unwrap();
+	/// ```
+	///
+	/// the `updating_with_query` observer runs before the write , it can change the update and
+	/// its error aborts it , by default it calls `updating`
 	///
 	/// NOTE : updated observer doesn't execute in this method
 	///
 	pub async fn update(
-		&self,
+		&mut self,
 		query: impl Into<Document>,
 		doc: impl Into<Document>,
 		options: impl Into<Option<UpdateOptions>>,
 	) -> MongodbResult<UpdateResult> {
-		self.collection.update_one(query.into(), doc.into(), options).await
-	}
-
-	pub async fn find(
-		&self,
-		filter: impl Into<Document>,
-		options: impl Into<Option<FindOptions>>,
-	) -> MongodbResult<Cursor<M>> {
-		self.collection.find(Some(filter.into()), options).await
+		let query = self.scope_filter(query.into());
+		let (mut doc, options) = (doc.into(), options.into());
+		// this must be pinned to handle recursive async call
+		Box::pin(M::updating_with_query(self, &query, &mut doc)).await?;
+		let result = self
+			.retrying("update", Some(&query), is_retryable_write, || {
+				self.collection.update_one(query.clone(), doc.clone(), options.clone())
+			})
+			.await;
+		self.invalidate_cache();
+		result
 	}
 
-	pub async fn find_and_collect(
-		&self,
-		filter: impl Into<Document>,
-		options: impl Into<Option<FindOptions>>,
-	) -> MongodbResult<Vec<MongodbResult<M>>> {
-		// TODO write this in other functions
-		let converted = filter.into();
-		let doc = if converted.is_empty() {
-			None
-		} else {
-			Some(converted)
-		};
-
-		let future = self.collection.find(doc, options).await?;
-		Ok(future.collect().await)
+	/// like `update` but the error is a `SparkError`
+	pub async fn try_update(
+		&mut self,
+		query: impl Into<Document>,
+		doc: impl Into<Document>,
+		options: impl Into<Option<UpdateOptions>>,
+	) -> SparkResult<UpdateResult> {
+		Ok(self.update(query, doc, options).await?)
 	}
 
-	pub fn register_attributes(&self, attributes: Vec<&str>) {
-		let mut attrs = attributes.iter().map(|attr| attr.to_string()).collect::<Vec<String>>();
-		let max_time_to_drop = Some(Duration::from_secs(5));
-		let (tx, _) = tokio::sync::oneshot::channel();
-		let db = self.db.clone();
-		let coll_name = self.collection_name.to_owned();
-		trace!("Spawn task to register indexes");
-		let register_attrs = async move {
-			let coll = db.collection::<M>(&coll_name);
-			let previous_indexes = coll
-				.list_indexes(Some(
-					ListIndexesOptions::builder().max_time(max_time_to_drop).build(),
+	/// like `update` but the write uses the session , like the session of `Spark::transaction` ,
+	/// the cache of `with_cache` is cleared by `dispatch_committed`
+	pub async fn update_with_session(
+		&mut self,
+		query: impl Into<Document>,
+		doc: impl Into<Document>,
+		options: impl Into<Option<UpdateOptions>>,
+		session: &mut ClientSession,
+	) -> MongodbResult<UpdateResult> {
+		let query = self.scope_filter(query.into());
+		let mut doc = doc.into();
+		// this must be pinned to handle recursive async call
+		Box::pin(M::updating_with_query(self, &query, &mut doc)).await?;
+		let result = self.collection.update_one_with_session(query, doc, options, session).await;
+		// a failed write can still be a part of the transaction until it's aborted
+		self.pending.push(Pending::Invalidated);
+		result
+	}
+
+	/// updates one document with an aggregation pipeline , unlike `update` the stages can
+	/// reference other fields of the document
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let updated = product_model
+	///     .update_pipeline(
+	///         doc! { "name": "book" },
+	///         vec![doc! { "$set": { "price": { "$multiply": ["$price", 1.1] } } }],
+	///         None,
+	///     )
+	///     .await?;
+	/// ```
+	///
+	/// NOTE : updated observer doesn't execute in this method
+	///
+	pub async fn update_pipeline(
+		&self,
+		query: impl Into<Document>,
+		pipeline: Vec<Document>,
+		options: impl Into<Option<UpdateOptions>>,
+	) -> MongodbResult<UpdateResult> {
+		let result =
+			self.collection.update_one(self.scope_filter(query.into()), pipeline, options).await;
+		self.invalidate_cache();
+		result
+	}
+
+	/// updates one document of the query and fills the model with it , `updated_at` is set by the
+	/// update and the `updated` observer is dispatched , nothing is dispatched when no document matches
+	///
+	/// the model is filled with the document before the update unless the `return_document` of
+	/// `options` is `ReturnDocument::After`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+	/// let mut user_model = User::new_model(None);
+	/// user_model
+	///     .find_one_and_update(doc! { "name": "Hossein" }, doc! { "$inc": { "age": 1 } }, options)
+	///     .await?;
+	/// ```
+	pub async fn find_one_and_update(
+		&mut self,
+		query: impl Into<Document>,
+		update: impl Into<Document>,
+		options: impl Into<Option<FindOneAndUpdateOptions>>,
+	) -> MongodbResult<Option<&mut Self>> {
+		let mut update = update.into();
+		// the update keeps its own updated_at if it sets one
+		let mut changes = Document::new();
+		if let Bson::Document(set) =
+			update.entry("$set".to_string()).or_insert_with(|| doc! {}.into())
+		{
+			if !set.contains_key(M::UPDATED_FIELD) {
+				set.insert(M::UPDATED_FIELD, DateTime::now());
+			}
+			changes = set.clone();
+		}
+
+		let query = self.scope_filter(query.into());
+		let options = options.into();
+		let found = self
+			.retrying("find_one_and_update", Some(&query), is_retryable_write, || {
+				self.collection.find_one_and_update(query.clone(), update.clone(), options.clone())
+			})
+			.await?;
+		self.invalidate_cache();
+		let Some(mut inner) = found else {
+			return Ok(None);
+		};
+		inner.after_load();
+		self.fill(inner);
+
+		let context = ObserverContext::updated(self.inner.model_id(), changes, None);
+		// this must be pinned to handle recursive async call
+		Box::pin(M::updated_with_context(self, &context)).await?;
+		Ok(Some(self))
+	}
+
+	/// updates the first document of the query with the fields of `changes` , the `$set` is made
+	/// from `changes` so it doesn't need to be written by hand
+	///
+	/// only the fields that aren't null are set , so the fields to patch should be `Option` and the
+	/// `None` ones are left as they are , a field can't be set to null with `patch` , use `update`
+	/// for it , `_id` and `created_at` of `changes` are never set and `updated_at` is set to now
+	///
+	/// unlike `update` , the `updated` observer is dispatched on this model when a document matched ,
+	/// the inner of this model isn't changed by the patch
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let changes = User { email: Some("new@mail.com".to_string()), ..Default::default() };
+	/// user_model.patch(doc! { "_id": id }, changes).await?;
+	/// ```
+	pub async fn patch(
+		&mut self,
+		query: impl Into<Document>,
+		changes: M,
+	) -> MongodbResult<UpdateResult> {
+		let mut set = to_document(&changes)?
+			.into_iter()
+			.filter(|(key, value)| {
+				key != "_id" && key != M::CREATED_FIELD && !matches!(value, Bson::Null)
+			})
+			.collect::<Document>();
+		set.insert(M::UPDATED_FIELD, DateTime::now());
+
+		let query = self.scope_filter(query.into());
+		let update = doc! { "$set": &set };
+		let result = self
+			.retrying("patch", Some(&query), is_retryable_write, || {
+				self.collection.update_one(query.clone(), update.clone(), None)
+			})
+			.await?;
+		self.invalidate_cache();
+		if result.matched_count > 0 {
+			if let Some(observer) = Spark::global_observer() {
+				observer.on_write(self.collection_name, WriteOp::Update, &set);
+			}
+			let context = ObserverContext::updated(None, set, None);
+			// this must be pinned to handle recursive async call
+			Box::pin(M::updated_with_context(self, &context)).await?;
+		}
+		Ok(result)
+	}
+
+	/// applies a different update to each filter in a single bulk write , instead of one round-trip
+	/// per update , the updates run in order and stop at the first failed one
+	///
+	/// every update must only use update operators like `$set` , it's checked before anything is
+	/// written because an update without operators replaces the whole document
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let result = user_model
+	///     .batch_update(vec![
+	///         (doc! { "_id": first_id }, doc! { "$set": { "age": 20 } }),
+	///         (doc! { "_id": second_id }, doc! { "$inc": { "age": 1 } }),
+	///     ])
+	///     .await?;
+	/// ```
+	///
+	/// NOTE : updated observer doesn't execute in this method
+	///
+	pub async fn batch_update(
+		&self,
+		updates: Vec<(Document, Document)>,
+	) -> MongodbResult<BulkWriteResult> {
+		for (index, (_, update)) in updates.iter().enumerate() {
+			check_update_operators(index, update)?;
+		}
+
+		let mut batches = Batches::<Document>::new(command_batching());
+		let mut result = BulkWriteResult::default();
+		let mut offset = 0;
+		for (filter, update) in updates {
+			let statement = doc! {
+				"q": self.scope_filter(filter),
+				"u": update,
+				"multi": false,
+			};
+			let size = mongodb::bson::to_vec(&statement)?.len();
+			if let Some(batch) = batches.push_sized(statement, size)? {
+				offset += self.update_batch(batch, offset, &mut result).await?;
+			}
+		}
+		if let Some(batch) = batches.finish() {
+			self.update_batch(batch, offset, &mut result).await?;
+		}
+		Ok(result)
+	}
+
+	/// runs one update command and adds its counts to the result , returns the count of statements
+	async fn update_batch(
+		&self,
+		statements: Vec<Document>,
+		offset: usize,
+		result: &mut BulkWriteResult,
+	) -> MongodbResult<usize> {
+		let count = statements.len();
+		let mut command = doc! {
+			"update": self.collection_name,
+			"updates": statements,
+			"ordered": true,
+		};
+		// the raw command doesn't get the write concern of the collection from the driver
+		if let Some(write_concern) = self.collection.write_concern() {
+			command.insert("writeConcern", to_bson(write_concern)?);
+		}
+		let response = run_write_command(&self.db, command).await;
+		// a part of the statements can be written even when the command fails
+		self.invalidate_cache();
+		let response = response.map_err(|error| offset_error(error, offset))?;
+		result.matched_count += response_count(&response, "n");
+		result.modified_count += response_count(&response, "nModified");
+		Ok(count)
+	}
+
+	/// runs a mix of inserts , updates and deletes with as few write commands as possible ,
+	/// the consecutive writes of the same kind share a command in an ordered bulk write ,
+	/// and an unordered one runs one command per kind
+	///
+	/// the inserted models are validated and get their timestamps , every update must only use
+	/// update operators , all of the writes are checked before anything is written , the indexes
+	/// in a bulk write error point into `ops`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let result = user_model
+	///     .bulk_write(
+	///         vec![
+	///             WriteOp::Insert(new_user),
+	///             WriteOp::UpdateOne {
+	///                 filter: doc! { "_id": changed_id },
+	///                 update: doc! { "$set": { "age": 30 } },
+	///             },
+	///             WriteOp::DeleteOne(doc! { "_id": removed_id }),
+	///         ],
+	///         BulkWriteOptions::unordered(),
+	///     )
+	///     .await?;
+	/// ```
+	///
+	/// NOTE : the model observers don't execute in this method , the global observer gets
+	/// the counts in `GlobalObserver::on_bulk_write` after all of the writes succeeded
+	///
+	pub async fn bulk_write(
+		&self,
+		ops: Vec<bulk::WriteOp<M>>,
+		options: impl Into<Option<BulkWriteOptions>>,
+	) -> MongodbResult<BulkWriteResult> {
+		let ordered = options.into().unwrap_or_default().ordered;
+		let now = DateTime::now();
+		let mut statements = Vec::with_capacity(ops.len());
+		for (index, op) in ops.into_iter().enumerate() {
+			let (kind, statement) = match op {
+				bulk::WriteOp::Insert(mut doc) => {
+					doc.validate()?;
+					doc.created_at(now);
+					doc.updated_at(now);
+					let mut document = to_document(&doc)?;
+					let id = match doc.model_id() {
+						Some(id) => id,
+						None => Bson::ObjectId(ObjectId::new()),
+					};
+					document.insert("_id", id);
+					(WriteKind::Insert, document)
+				}
+				bulk::WriteOp::UpdateOne {
+					filter,
+					update,
+				} => {
+					check_update_operators(index, &update)?;
+					let statement = doc! {
+						"q": self.scope_filter(filter),
+						"u": update,
+						"multi": false,
+					};
+					(WriteKind::Update, statement)
+				}
+				bulk::WriteOp::DeleteOne(filter) => {
+					let statement = doc! {
+						"q": self.scope_filter(filter),
+						"limit": 1,
+					};
+					(WriteKind::Delete, statement)
+				}
+			};
+			statements.push((kind, index, statement));
+		}
+		// the sort is stable so the writes of a kind keep their order
+		if !ordered {
+			statements.sort_by_key(|(kind, ..)| *kind);
+		}
+
+		let mut commands = Vec::new();
+		let mut current: Option<(WriteKind, Batches<(usize, Document)>)> = None;
+		for (kind, index, statement) in statements {
+			let size = mongodb::bson::to_vec(&statement)?.len();
+			let batches = match &mut current {
+				Some((current_kind, batches)) if *current_kind == kind => batches,
+				_ => {
+					if let Some((current_kind, batches)) = current.take() {
+						commands.extend(batches.finish().map(|batch| (current_kind, batch)));
+					}
+					&mut current.insert((kind, Batches::new(command_batching()))).1
+				}
+			};
+			if let Some(batch) = batches.push_sized((index, statement), size)? {
+				commands.push((kind, batch));
+			}
+		}
+		if let Some((kind, batches)) = current {
+			commands.extend(batches.finish().map(|batch| (kind, batch)));
+		}
+
+		let mut result = BulkWriteResult::default();
+		let mut failure: Option<mongodb::error::Error> = None;
+		for (kind, batch) in commands {
+			if let Err(error) = self.bulk_batch(kind, batch, ordered, &mut result).await {
+				// an unordered bulk write goes on after the failed writes of a command ,
+				// but not after an error like a lost connection
+				if ordered || !matches!(*error.kind, ErrorKind::BulkWrite(_)) {
+					return Err(error);
+				}
+				match &mut failure {
+					Some(first) => merge_failure(first, error),
+					None => failure = Some(error),
+				}
+			}
+		}
+		if let Some(error) = failure {
+			return Err(error);
+		}
+		if let Some(observer) = Spark::global_observer() {
+			observer.on_bulk_write(self.collection_name, &result);
+		}
+		Ok(result)
+	}
+
+	/// runs one write command of `bulk_write` and adds its counts to the result
+	async fn bulk_batch(
+		&self,
+		kind: WriteKind,
+		batch: Vec<(usize, Document)>,
+		ordered: bool,
+		result: &mut BulkWriteResult,
+	) -> MongodbResult<()> {
+		let (name, field) = kind.command();
+		let (indexes, statements): (Vec<usize>, Vec<Document>) = batch.into_iter().unzip();
+		let mut command = doc! {
+			name: self.collection_name,
+			field: statements,
+			"ordered": ordered,
+		};
+		// the raw command doesn't get the write concern of the collection from the driver
+		if let Some(write_concern) = self.collection.write_concern() {
+			command.insert("writeConcern", to_bson(write_concern)?);
+		}
+		let response = run_write_command(&self.db, command).await;
+		// a part of the statements can be written even when the command fails
+		self.invalidate_cache();
+		let response = response.map_err(|error| reindex_error(error, &indexes))?;
+		kind.add_counts(&response, result);
+		Ok(())
+	}
+
+	/// returns the raw cursor , `AfterLoad::after_load` isn't called on its documents
+	pub async fn find(
+		&self,
+		filter: impl Into<Document>,
+		options: impl Into<Option<FindOptions>>,
+	) -> MongodbResult<Cursor<M>> {
+		let filter = self.read_filter(filter.into());
+		let options = options.into();
+		self.retrying("find", Some(&filter), is_retryable_read, || {
+			self.collection.find(Some(filter.clone()), options.clone())
+		})
+		.await
+	}
+
+	/// streams the models of the filter without collecting them , `AfterLoad::after_load` is called
+	/// on each of them , an empty filter is handled the same as `find_and_collect`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let mut users = user_model.find_stream(doc! { "age": { "$gt": 18 } }, None).await?;
+	/// while let Some(user) = users.next().await {
+	///     println!("{:?}", user?);
+	/// }
+	/// ```
+	pub async fn find_stream(
+		&self,
+		filter: impl Into<Document>,
+		options: impl Into<Option<FindOptions>>,
+	) -> MongodbResult<impl Stream<Item = MongodbResult<M>> + Send + Unpin> {
+		let filter = self.find_filter(filter.into());
+		let options = options.into();
+		let cursor = self
+			.retrying("find", filter.as_ref(), is_retryable_read, || {
+				self.collection.find(filter.clone(), options.clone())
+			})
+			.await?;
+		Ok(cursor.map_ok(|mut inner| {
+			inner.after_load();
+			inner
+		}))
+	}
+
+	/// finds the models whose `field` equals `value` without the case , like the users whose email
+	/// is `John@Mail.com` or `john@mail.com` , it uses the `index::case_insensitive()` collation
+	///
+	/// for a single field use an index with the same collation , otherwise the query scans the
+	/// collection , the other collations can be set with `query().collation(..)` or the
+	/// `collation` of `FindOptions` and `FindOneOptions`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let users = user_model.find_ci("email", "John@Mail.com").await?;
+	/// ```
+	pub async fn find_ci(&self, field: &str, value: impl Into<Bson>) -> MongodbResult<Vec<M>> {
+		self.query()
+			.filter(doc! { field: value.into() })
+			.collation(case_insensitive())
+			.collect()
+			.await
+	}
+
+	/// finds the models that match the term in the text index of `register_text_index` , the most
+	/// relevant first , the sort of the options is used after the relevance
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let products = product_model.search("wireless headphones", None).await?;
+	/// ```
+	pub async fn search(
+		&self,
+		term: &str,
+		options: impl Into<Option<FindOptions>>,
+	) -> MongodbResult<Vec<M>> {
+		let scored = self.search_scored(term, options).await?;
+		Ok(scored.into_iter().map(|(inner, _)| inner).collect())
+	}
+
+	/// like `search` but every model comes with its relevance score , a higher score is more relevant
+	pub async fn search_scored(
+		&self,
+		term: &str,
+		options: impl Into<Option<FindOptions>>,
+	) -> MongodbResult<Vec<(M, f64)>> {
+		let score = doc! { "$meta": "textScore" };
+		let mut options = options.into().unwrap_or_default();
+		let mut projection = options.projection.take().unwrap_or_default();
+		projection.insert(TEXT_SCORE_FIELD, score.clone());
+		options.projection = Some(projection);
+		let mut sort = doc! { TEXT_SCORE_FIELD: score };
+		sort.extend(options.sort.take().unwrap_or_default());
+		options.sort = Some(sort);
+
+		let filter = self.read_filter(doc! { "$text": { "$search": term } });
+		let collection = self.collection.clone_with_type::<Document>();
+		let mut cursor = self
+			.retrying("search", Some(&filter), is_retryable_read, || {
+				collection.find(filter.clone(), options.clone())
+			})
+			.await?;
+
+		let mut scored = Vec::new();
+		while let Some(mut document) = cursor.try_next().await? {
+			let score = document.remove(TEXT_SCORE_FIELD).and_then(|score| score.as_f64());
+			let mut inner = from_document::<M>(document)?;
+			inner.after_load();
+			scored.push((inner, score.unwrap_or_default()));
+		}
+		Ok(scored)
+	}
+
+	/// finds the models whose GeoJSON `field` is at most `max_meters` from the point , the nearest
+	/// first , the field needs the index of `register_geo_index`
+	///
+	/// the coordinates are checked before the query , the longitude must be between -180 and 180
+	/// and the latitude between -90 and 90
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// // the places in 500 meters of the Eiffel Tower
+	/// let places = place_model.find_near("location", 2.2945, 48.8584, 500.0).await?;
+	/// ```
+	pub async fn find_near(
+		&self,
+		field: &str,
+		longitude: f64,
+		latitude: f64,
+		max_meters: f64,
+	) -> MongodbResult<Vec<M>> {
+		if !(-180.0..=180.0).contains(&longitude) || !(-90.0..=90.0).contains(&latitude) {
+			return Err(Error::new(&format!(
+				"The point ({longitude} , {latitude}) is invalid , the longitude must be between -180 and 180 and the latitude between -90 and 90"
+			))
+			.into());
+		}
+		if !(max_meters >= 0.0 && max_meters.is_finite()) {
+			return Err(Error::new(&format!(
+				"The distance {max_meters} is invalid , it must be a positive number of meters"
+			))
+			.into());
+		}
+		let near = doc! {
+			"$geometry": { "type": "Point", "coordinates": [longitude, latitude] },
+			"$maxDistance": max_meters,
+		};
+		self.query().filter(doc! { field: { "$near": near } }).collect().await
+	}
+
+	/// the GridFS bucket of the database of the model , for the files that the model references
+	/// by id , `None` name is the default `fs` bucket
+	pub fn gridfs_bucket(&self, name: Option<&str>) -> Bucket {
+		Bucket::new(&self.db, name)
+	}
+
+	/// starts a find with chained filter , sorts , projection , skip and limit instead of the
+	/// `FindOptions` documents
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let users = user_model.query().filter(doc! { "age": 18 }).sort_asc("name").limit(10).collect().await?;
+	/// ```
+	pub fn query(&self) -> Query<'_, 'a, M> {
+		Query::new(self)
+	}
+
+	/// streams the documents of the filter ordered by `sort_field` and survives the cursor being
+	/// killed by the server , for example by the cursor timeout during a long export
+	///
+	/// when the cursor is lost the query is issued again for the documents after the last seen value
+	/// of `sort_field` , so `sort_field` must be unique and present in every document , `_id` is
+	/// the usual choice
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let mut users = user_model.find_resilient_stream(doc! {}, "_id");
+	/// while let Some(user) = users.next().await {
+	///     export(user?).await;
+	/// }
+	/// ```
+	pub fn find_resilient_stream(
+		&self,
+		filter: impl Into<Document>,
+		sort_field: &str,
+	) -> impl Stream<Item = MongodbResult<M>> + Send + Unpin + 'static
+	where
+		M: 'static,
+	{
+		resilient_stream(
+			self.collection.clone_with_type::<Document>(),
+			self.read_filter(filter.into()),
+			sort_field,
+		)
+	}
+
+	pub async fn find_and_collect(
+		&self,
+		filter: impl Into<Document>,
+		options: impl Into<Option<FindOptions>>,
+	) -> MongodbResult<Vec<MongodbResult<M>>> {
+		let filter = self.find_filter(filter.into());
+		let options = options.into();
+		#[cfg(feature = "cache")]
+		if let Some(cache) = &self.cache {
+			let key =
+				QueryCache::key("find", filter.as_ref().unwrap_or(&Document::new()), &options);
+			let documents = match cache.get(&key) {
+				Some(documents) => {
+					Operation::cache_hit(self.collection_name, "find", filter.as_ref());
+					documents
+				}
+				None => {
+					let collection = self.collection.clone_with_type::<Document>();
+					let cursor = self
+						.retrying("find", filter.as_ref(), is_retryable_read, || {
+							collection.find(filter.clone(), options.clone())
+						})
+						.await?;
+					let documents: Vec<Document> = cursor.try_collect().await?;
+					cache.insert(key, documents.clone());
+					documents
+				}
+			};
+			return Ok(documents.into_iter().map(Self::loaded).collect());
+		}
+		let future = self
+			.retrying("find", filter.as_ref(), is_retryable_read, || {
+				self.collection.find(filter.clone(), options.clone())
+			})
+			.await?;
+		Ok(future
+			.map(|result| {
+				result.map(|mut inner| {
+					inner.after_load();
+					inner
+				})
+			})
+			.collect()
+			.await)
+	}
+
+	/// calls `f` with the models of the filter in batches of up to `batch_size` as the cursor is
+	/// read , only one batch is kept in memory , so it's for the results that are too big for
+	/// `find_and_collect` , `AfterLoad::after_load` is called on each model
+	///
+	/// the cursor fetches `batch_size` documents at a time too , it stops at the first document
+	/// that can't be loaded or the first error of `f` and returns it
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// user_model
+	///     .for_each_batch(doc! { "active": true }, 500, |users| async move {
+	///         export(users).await
+	///     })
+	///     .await?;
+	/// ```
+	pub async fn for_each_batch<F, Fut>(
+		&self,
+		filter: impl Into<Document>,
+		batch_size: usize,
+		mut f: F,
+	) -> MongodbResult<()>
+	where
+		F: FnMut(Vec<M>) -> Fut,
+		Fut: Future<Output = MongodbResult<()>>,
+	{
+		if batch_size == 0 {
+			return Err(
+				Error::new("The batch size of for_each_batch must be greater than 0").into()
+			);
+		}
+		let options = FindOptions::builder()
+			.batch_size(u32::try_from(batch_size).unwrap_or(u32::MAX))
+			.build();
+		let mut models = self.find_stream(filter, options).await?;
+		let mut batch = Vec::with_capacity(batch_size);
+		while let Some(inner) = models.try_next().await? {
+			batch.push(inner);
+			if batch.len() == batch_size {
+				f(std::mem::replace(&mut batch, Vec::with_capacity(batch_size))).await?;
+			}
+		}
+		if !batch.is_empty() {
+			f(batch).await?;
+		}
+		Ok(())
+	}
+
+	/// loads only the fields of the documents of the filter and deserializes them into `P` , a
+	/// smaller struct than `M` , so the rest of a big document isn't sent or parsed
+	///
+	/// `_id` is loaded unless it's excluded with `-_id` , like in a projection of MongoDB ,
+	/// `AfterLoad::after_load` isn't called because `P` isn't the model
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// #[derive(Deserialize)]
+	/// struct UserRow {
+	///     name: String,
+	///     email: String,
+	/// }
+	///
+	/// let rows: Vec<UserRow> = user_model
+	///     .find_projected(doc! { "active": true }, &["name", "email", "-_id"])
+	///     .await?;
+	/// ```
+	pub async fn find_projected<P>(
+		&self,
+		filter: impl Into<Document>,
+		fields: &[&str],
+	) -> MongodbResult<Vec<P>>
+	where
+		P: DeserializeOwned + Send + Sync + Unpin,
+	{
+		let projection = projection(fields).map_err(|message| Error::new(&message))?;
+		let filter = self.find_filter(filter.into());
+		let options = FindOptions::builder().projection(projection).build();
+		let collection = self.collection.clone_with_type::<P>();
+		let cursor = self
+			.retrying("find", filter.as_ref(), is_retryable_read, || {
+				collection.find(filter.clone(), options.clone())
+			})
+			.await?;
+		cursor.try_collect().await
+	}
+
+	/// returns the distinct values of `field` in the documents of the filter , every value is
+	/// deserialized into `T` , None or an empty filter takes the whole collection
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let statuses: Vec<String> = order_model.distinct("status", None, None).await?;
+	/// ```
+	pub async fn distinct<T: DeserializeOwned>(
+		&self,
+		field: &str,
+		filter: impl Into<Option<Document>>,
+		options: impl Into<Option<DistinctOptions>>,
+	) -> MongodbResult<Vec<T>> {
+		let filter = self.find_filter(filter.into().unwrap_or_default());
+		let values = self.collection.distinct(field, filter, options).await?;
+		values
+			.into_iter()
+			.map(|value| {
+				from_bson(value).map_err(|error| {
+					Error::new(&format!("A distinct value of {field} can't be converted : {error}"))
+						.into()
+				})
+			})
+			.collect()
+	}
+
+	/// counts the documents of the filter , an empty filter counts the whole collection
+	pub async fn count(
+		&self,
+		filter: impl Into<Document>,
+		options: impl Into<Option<CountOptions>>,
+	) -> MongodbResult<u64> {
+		let filter = self.read_filter(filter.into());
+		let options = options.into();
+		self.retrying("count", Some(&filter), is_retryable_read, || {
+			self.collection.count_documents(filter.clone(), options.clone())
+		})
+		.await
+	}
+
+	/// returns true if any document matches the filter , the count stops at the first match
+	pub async fn exists(&self, filter: impl Into<Document>) -> MongodbResult<bool> {
+		let options = CountOptions::builder().limit(1).build();
+		Ok(self.count(filter, options).await? > 0)
+	}
+
+	/// returns the `page` of the filter with `per_page` models in each page and the total count
+	/// of the filter , the pages start from 1
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let page = user_model.paginate(doc! {}, 1, 20).await?;
+	/// ```
+	pub async fn paginate(
+		&self,
+		filter: impl Into<Document>,
+		page: u64,
+		per_page: u64,
+	) -> MongodbResult<Page<M>> {
+		if page == 0 || per_page == 0 {
+			return Err(Error::new(&format!(
+				"The page and per_page must be at least 1 , got page {page} and per_page {per_page}"
+			))
+			.into());
+		}
+		let skip = (page - 1).checked_mul(per_page).ok_or_else(|| {
+			Error::new(&format!("The page {page} is too far for {per_page} per page"))
+		})?;
+
+		let filter = self.read_filter(filter.into());
+		let total = self.collection.count_documents(filter.clone(), None).await?;
+		let options = FindOptions::builder()
+			.skip(skip)
+			.limit(i64::try_from(per_page).unwrap_or(i64::MAX))
+			.build();
+		let items = self.find_models(filter, Some(options)).await?;
+		Ok(Page::new(items, total, page, per_page))
+	}
+
+	/// returns the `limit` newest documents , newest first by the `created_at` timestamp
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let latest_users = User::new_model(None).recent(10).await?;
+	/// ```
+	pub async fn recent(&self, limit: u64) -> MongodbResult<Vec<M>> {
+		let options = FindOptions::builder()
+			.sort(doc! { M::CREATED_FIELD: -1 })
+			.limit(i64::try_from(limit).unwrap_or(i64::MAX))
+			.build();
+		self.find_models(doc! {}, Some(options)).await
+	}
+
+	/// finds the documents that have at least one element in `array_field` matching all of
+	/// the `conditions` , it builds `{ array_field: { $elemMatch: conditions } }`
+	///
+	/// without `$elemMatch` every condition can match a different element of the array
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// // orders that have one item which is both a book and more than 10
+	/// let orders = order_model
+	///     .find_elem_match("items", doc! { "kind": "book", "price": { "$gt": 10 } }, None)
+	///     .await?;
+	/// ```
+	pub async fn find_elem_match(
+		&self,
+		array_field: &str,
+		conditions: Document,
+		options: impl Into<Option<FindOptions>>,
+	) -> MongodbResult<Vec<M>> {
+		let filter = doc! {
+			array_field: {
+				"$elemMatch": conditions
+			}
+		};
+		self.find_models(filter, options.into()).await
+	}
+
+	/// runs a `$graphLookup` over the collection , every document gets an `as_field` array with
+	/// the documents reached by recursively matching `connect_from` of a document to `connect_to`
+	/// of the next one
+	///
+	/// # Arguments
+	///
+	/// * `start_with`: the value to start the search with , it can be a field path like `"$parent_id"`
+	/// * `connect_from`: the field whose value is followed to the next document
+	/// * `connect_to`: the field of the other documents that is matched against `connect_from`
+	/// * `as_field`: the name of the array field that holds the reached documents
+	/// * `max_depth`: the maximum recursion depth , `None` means no limit
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// // every category with all of its ancestors
+	/// let categories = category_model
+	///     .graph_lookup(Bson::from("$parent_id"), "parent_id", "_id", "ancestors", None)
+	///     .await?;
+	/// ```
+	pub async fn graph_lookup(
+		&self,
+		start_with: Bson,
+		connect_from: &str,
+		connect_to: &str,
+		as_field: &str,
+		max_depth: Option<i32>,
+	) -> MongodbResult<Vec<Document>> {
+		let mut graph_lookup = doc! {
+			"from": self.collection_name,
+			"startWith": start_with,
+			"connectFromField": connect_from,
+			"connectToField": connect_to,
+			"as": as_field,
+		};
+		if let Some(depth) = max_depth {
+			graph_lookup.insert("maxDepth", depth);
+		}
+
+		let mut pipeline = Vec::new();
+		if let Some(scope) = &self.scope {
+			// the recursion must not leave the tenant either
+			graph_lookup.insert("restrictSearchWithMatch", scope.clone());
+			pipeline.push(doc! { "$match": scope.clone() });
+		}
+		pipeline.push(doc! { "$graphLookup": graph_lookup });
+
+		self.collection.aggregate(pipeline, None).await?.try_collect().await
+	}
+
+	/// loads the models of `target` that the ObjectId `field` of the items references , like the
+	/// user of every order , the result has the model of each item in the order of `items`
+	///
+	/// all references are loaded with one `$in` query , so populating 100 orders is 1 query ,
+	/// a reference that matches nothing , for example a deleted user , is None
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let orders = order_model.find_and_collect(doc! {}, None).await?;
+	/// let orders = orders.into_iter().collect::<MongodbResult<Vec<Order>>>()?;
+	/// let users = order_model.populate(&orders, "user_id", &User::new_model(None)).await?;
+	/// for (order, user) in orders.iter().zip(users) {
+	///     println!("{:?} ordered by {:?}", order.sku, user.map(|user| user.name));
+	/// }
+	/// ```
+	pub async fn populate<U>(
+		&self,
+		items: &[M],
+		field: &str,
+		target: &Model<'_, U>,
+	) -> MongodbResult<Vec<Option<U>>>
+	where
+		U: Default,
+		U: Serialize,
+		U: DeserializeOwned,
+		U: Send,
+		U: Sync,
+		U: Unpin,
+		U: Debug,
+		U: ModelTimestamps,
+		U: ModelId,
+		U: Observer<U>,
+		U: AfterLoad,
+		U: SoftDelete,
+		U: Validate,
+		U: Clone,
+	{
+		Ok(self
+			.populate_many(items, field, target)
+			.await?
+			.into_iter()
+			.map(|mut references| references.pop().flatten())
+			.collect())
+	}
+
+	/// like `populate` for a field that holds an array of ObjectIds , like the tags of every post ,
+	/// the models of each item are in the order of its array
+	pub async fn populate_many<U>(
+		&self,
+		items: &[M],
+		field: &str,
+		target: &Model<'_, U>,
+	) -> MongodbResult<Vec<Vec<Option<U>>>>
+	where
+		U: Default,
+		U: Serialize,
+		U: DeserializeOwned,
+		U: Send,
+		U: Sync,
+		U: Unpin,
+		U: Debug,
+		U: ModelTimestamps,
+		U: ModelId,
+		U: Observer<U>,
+		U: AfterLoad,
+		U: SoftDelete,
+		U: Validate,
+		U: Clone,
+	{
+		let references = items
+			.iter()
+			.map(|item| reference_ids(item, field))
+			.collect::<MongodbResult<Vec<_>>>()?;
+
+		let mut ids = references.iter().flatten().copied().collect::<Vec<ObjectId>>();
+		ids.sort_unstable();
+		ids.dedup();
+
+		let mut loaded = HashMap::new();
+		if !ids.is_empty() {
+			let mut found = target.find_stream(doc! { "_id": { "$in": ids } }, None).await?;
+			while let Some(inner) = found.try_next().await? {
+				if let Some(Bson::ObjectId(id)) = inner.model_id() {
+					loaded.insert(id, inner);
+				}
+			}
+		}
+
+		Ok(references
+			.into_iter()
+			.map(|ids| ids.iter().map(|id| loaded.get(id).cloned()).collect())
+			.collect())
+	}
+
+	/// runs the aggregation pipeline and deserializes every result into `T` , it can be another
+	/// type than the model , like the summary of a `$group` stage
+	///
+	/// the pipeline runs after a `$match` of the tenant scope and the soft delete , a result that
+	/// doesn't match `T` fails the whole call instead of being dropped
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// #[derive(Deserialize)]
+	/// struct AgeGroup {
+	///     _id: u64,
+	///     count: u64,
+	/// }
+	///
+	/// let groups: Vec<AgeGroup> = user_model
+	///     .aggregate(vec![doc! { "$group": { "_id": "$age", "count": { "$sum": 1 } } }], None)
+	///     .await?;
+	/// ```
+	pub async fn aggregate<T: DeserializeOwned>(
+		&self,
+		pipeline: Vec<Document>,
+		options: impl Into<Option<AggregateOptions>>,
+	) -> MongodbResult<Vec<T>> {
+		self.aggregate_stream(pipeline, options).await?.try_collect().await
+	}
+
+	/// like `aggregate` but returns a stream of the results instead of collecting them , every
+	/// item is a result so the rows that don't match `T` are reported one by one
+	pub async fn aggregate_stream<T: DeserializeOwned>(
+		&self,
+		pipeline: Vec<Document>,
+		options: impl Into<Option<AggregateOptions>>,
+	) -> MongodbResult<impl Stream<Item = MongodbResult<T>> + Send + Unpin> {
+		let filter = self.read_filter(doc! {});
+		let stages = if filter.is_empty() {
+			pipeline
+		} else {
+			std::iter::once(doc! { "$match": filter }).chain(pipeline).collect()
+		};
+
+		let options = options.into();
+		let cursor = self
+			.retrying("aggregate", None, is_retryable_read, || {
+				self.collection.aggregate(stages.clone(), options.clone())
+			})
+			.await?;
+		Ok(cursor.map(|result| result.and_then(|document| Ok(from_document(document)?))))
+	}
+
+	/// streams the changes of the collection , like the writes of other services , the pipeline
+	/// can filter or reshape the events , ex : `doc! { "$match": { "operationType": "insert" } }`
+	///
+	/// the full document of every change is loaded into `M` and `AfterLoad::after_load` is called
+	/// on it , the resume token of each event can be persisted to continue after a restart with
+	/// `ChangeStreamOptions::resume_after`
+	///
+	/// on a scoped model only the changes whose full document is in the tenant are streamed , so
+	/// the updates need `FullDocumentType::UpdateLookup` and the deletes aren't streamed ,
+	/// change streams need a replica set or sharded cluster
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let mut changes = user_model.watch(vec![], None).await?;
+	/// while let Some(change) = changes.next().await {
+	///     let change = change?;
+	///     println!("{:?} {:?}", change.operation, change.document_key);
+	/// }
+	/// ```
+	pub async fn watch(
+		&self,
+		pipeline: Vec<Document>,
+		options: impl Into<Option<ChangeStreamOptions>>,
+	) -> MongodbResult<impl Stream<Item = MongodbResult<ChangeEvent<M>>> + Send + Unpin> {
+		let stages = match &self.scope {
+			Some(scope) => {
+				let filter = scope
+					.iter()
+					.map(|(key, value)| (format!("fullDocument.{key}"), value.clone()))
+					.collect::<Document>();
+				std::iter::once(doc! { "$match": filter }).chain(pipeline).collect()
+			}
+			None => pipeline,
+		};
+
+		let stream = self.collection.watch(stages, options).await?;
+		Ok(stream.map_ok(|event| {
+			let mut change = ChangeEvent::from(event);
+			if let Some(inner) = &mut change.full_document {
+				inner.after_load();
+			}
+			change
+		}))
+	}
+
+	/// creates the collection as a time-series collection , it does nothing if the time-series
+	/// collection already exists
+	///
+	/// a regular collection can't be converted , so this must run before anything is inserted ,
+	/// it returns an error if a regular collection with the same name exists
+	///
+	/// # Arguments
+	///
+	/// * `time_field`: the field that holds the time of each measurement , it must be a BSON date
+	/// * `meta_field`: the field that describes the series , like a sensor id
+	/// * `granularity`: the expected interval between measurements , `None` means seconds
+	pub async fn ensure_time_series(
+		&self,
+		time_field: &str,
+		meta_field: Option<&str>,
+		granularity: Option<TimeseriesGranularity>,
+	) -> MongodbResult<()> {
+		let mut existing =
+			self.db.list_collections(doc! { "name": self.collection_name }, None).await?;
+		if let Some(specification) = existing.try_next().await? {
+			return match specification.collection_type {
+				CollectionType::Timeseries => Ok(()),
+				_ => Err(Error::new(&format!(
+					"The collection {} already exists and isn't a time-series collection",
+					self.collection_name
 				))
-				.await;
-
-			let mut keys_to_remove = Vec::new();
-
-			if previous_indexes.is_ok() {
-				let foreach_future = previous_indexes.unwrap().for_each(|pr| {
-					match pr {
-						Ok(index_model) => {
-							index_model.keys.iter().for_each(|key| {
-								if key.0 != "_id" {
-									if let Some(pos) = attrs.iter().position(|k| k == key.0) {
-										// means attribute exists in struct and database and not need to create it
-										attrs.remove(pos);
-									} else if let Some(rw) = &index_model.options {
-										// means the attribute must remove because not exists in struct
-										keys_to_remove.push(rw.name.clone())
-									}
-								}
-							});
-						}
-						Err(error) => {
-							error!("Can't unpack index model {error}");
-						}
-					}
-					futures::future::ready(())
-				});
-				foreach_future.await;
-			}
-			let attrs = attrs
-				.iter()
-				.map(|attr| {
-					let key = attr.to_string();
-					IndexModel::builder()
-						.keys(doc! {
-							key : 1
-						})
-						.build()
+				.into()),
+			};
+		}
+
+		let timeseries = TimeseriesOptions::builder()
+			.time_field(time_field.to_string())
+			.meta_field(meta_field.map(str::to_string))
+			.granularity(granularity)
+			.build();
+		self.db
+			.create_collection(
+				self.collection_name,
+				CreateCollectionOptions::builder().timeseries(timeseries).build(),
+			)
+			.await
+	}
+
+	/// samples up to `sample_size` random documents and checks them against the model , it reports
+	/// the documents that fail to deserialize with their offending fields , the stored fields that
+	/// the model doesn't have and the model fields that the documents don't have
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let report = user_model.schema_audit(500).await?;
+	/// if !report.is_clean() {
+	///     println!("The users collection drifted {report:?}");
+	/// }
+	/// ```
+	pub async fn schema_audit(&self, sample_size: usize) -> MongodbResult<SchemaReport> {
+		let mut pipeline = Vec::new();
+		if let Some(scope) = &self.scope {
+			pipeline.push(doc! { "$match": scope.clone() });
+		}
+		pipeline
+			.push(doc! { "$sample": { "size": i64::try_from(sample_size).unwrap_or(i64::MAX) } });
+
+		let mut report = SchemaReport::default();
+		let mut cursor = self.collection.aggregate(pipeline, None).await?;
+		while let Some(stored) = cursor.try_next().await? {
+			report.audit::<M>(stored);
+		}
+		Ok(report)
+	}
+
+	/// overrides the limits that `insert_many` and `insert_stream` use to split the documents
+	/// into several `insert_many` calls , by default they follow the server limits
+	pub fn with_batching(mut self, batching: InsertBatching) -> Self {
+		self.batching = batching;
+		self
+	}
+
+	/// stops `save_many` from dispatching the `created` observer of every document ,
+	/// it's useful for bulk loads where N observers defeat the purpose
+	pub fn without_observers(mut self) -> Self {
+		self.bulk_observers = false;
+		self
+	}
+
+	/// inserts all documents like `insert_many` but it sets `created_at` on each document and
+	/// dispatches the `created` observer per document , returns the inserted ids in the order
+	/// of `docs`
+	///
+	/// an empty `docs` is a no-op , when some documents fail the indexes in the bulk write error
+	/// point into `docs` , the documents are validated first and an invalid one fails the whole call
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let mut user_model = User::new_model(None).without_observers();
+	/// let ids = user_model.save_many(users, None).await?;
+	/// ```
+	pub async fn save_many(
+		&mut self,
+		docs: Vec<M>,
+		options: impl Into<Option<InsertManyOptions>>,
+	) -> MongodbResult<Vec<Id>> {
+		if docs.is_empty() {
+			return Ok(Vec::new());
+		}
+		let options = options.into();
+		let mut ids = Vec::with_capacity(docs.len());
+		let mut documents = Vec::with_capacity(docs.len());
+		// nothing is written when one of the documents is invalid
+		docs.iter().try_for_each(Validate::validate)?;
+		let now = DateTime::now();
+		for mut doc in docs {
+			doc.created_at(now);
+			doc.updated_at(now);
+			let mut document = to_document(&doc)?;
+			// the ids are made here so they are known without reading the result and
+			// the observers see the saved document
+			let id = match doc.model_id() {
+				Some(id) => id,
+				None => Bson::ObjectId(ObjectId::new()),
+			};
+			document.insert("_id", id.clone());
+			ids.push(id);
+			documents.push(document);
+		}
+
+		let mut batches = Batches::new(self.batching);
+		let mut offset = 0;
+		let mut result = None;
+		for document in &documents {
+			if let Some(batch) = batches.push(RawDocumentBuf::from_document(document)?)? {
+				offset += self.insert_batch(batch, offset, options.clone(), &mut result).await?;
+			}
+		}
+		self.insert_rest(batches, offset, options, result).await?;
+
+		if self.bulk_observers {
+			for (document, id) in documents.into_iter().zip(ids.iter()) {
+				let mut model = self.with_inner(from_document(document)?);
+				let context = ObserverContext::created(id.clone());
+				// this must be pinned to handle recursive async call
+				Box::pin(M::created_with_context(&mut model, &context)).await?;
+			}
+		}
+		Ok(ids)
+	}
+
+	/// like `save_many` but the error is a `SparkError`
+	pub async fn try_save_many(
+		&mut self,
+		docs: Vec<M>,
+		options: impl Into<Option<InsertManyOptions>>,
+	) -> SparkResult<Vec<Id>> {
+		Ok(self.save_many(docs, options).await?)
+	}
+
+	/// inserts all documents , the documents are split into batches by count and by
+	/// their serialized size , so a big input doesn't exceed the server message limit
+	///
+	/// the returned `inserted_ids` are keyed by the index of the document in `docs` ,
+	/// also the indexes in a bulk write error point into `docs`
+	///
+	/// NOTE : created observer doesn't execute in this method
+	pub async fn insert_many(
+		&self,
+		docs: impl IntoIterator<Item = impl Borrow<M>>,
+		options: impl Into<Option<InsertManyOptions>>,
+	) -> MongodbResult<InsertManyResult> {
+		let options = options.into();
+		let mut batches = Batches::new(self.batching);
+		let mut offset = 0;
+		let mut result = None;
+		for doc in docs {
+			if let Some(batch) = batches.push(to_raw_document_buf(doc.borrow())?)? {
+				offset += self.insert_batch(batch, offset, options.clone(), &mut result).await?;
+			}
+		}
+		self.insert_rest(batches, offset, options, result).await
+	}
+
+	/// like `insert_many` but the error is a `SparkError`
+	pub async fn try_insert_many(
+		&self,
+		docs: impl IntoIterator<Item = impl Borrow<M>>,
+		options: impl Into<Option<InsertManyOptions>>,
+	) -> SparkResult<InsertManyResult> {
+		Ok(self.insert_many(docs, options).await?)
+	}
+
+	/// like `insert_many` but pulls the documents from a stream , each batch is inserted as soon
+	/// as it's full so the whole input is never held in memory
+	///
+	/// NOTE : created observer doesn't execute in this method
+	pub async fn insert_stream(
+		&self,
+		docs: impl Stream<Item = impl Borrow<M>>,
+		options: impl Into<Option<InsertManyOptions>>,
+	) -> MongodbResult<InsertManyResult> {
+		let options = options.into();
+		let mut docs = std::pin::pin!(docs);
+		let mut batches = Batches::new(self.batching);
+		let mut offset = 0;
+		let mut result = None;
+		while let Some(doc) = docs.next().await {
+			if let Some(batch) = batches.push(to_raw_document_buf(doc.borrow())?)? {
+				offset += self.insert_batch(batch, offset, options.clone(), &mut result).await?;
+			}
+		}
+		self.insert_rest(batches, offset, options, result).await
+	}
+
+	/// writes the documents of the filter to `writer` as a sequence of BSON documents , it's the
+	/// same format that mongodump writes to a `.bson` file , returns the count of written documents
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// let mut backup = Vec::new();
+	/// let exported = user_model.export_bson(doc! {}, &mut backup).await?;
+	/// let imported = restore_model.import_bson(backup.as_slice()).await?;
+	/// ```
+	pub async fn export_bson(
+		&self,
+		filter: impl Into<Document>,
+		writer: impl AsyncWrite,
+	) -> MongodbResult<u64> {
+		let mut writer = std::pin::pin!(writer);
+		let mut cursor = self
+			.collection
+			.clone_with_type::<RawDocumentBuf>()
+			.find(self.scope_filter(filter.into()), None)
+			.await?;
+		let mut count = 0;
+		while let Some(document) = cursor.try_next().await? {
+			write_document(&mut writer, &document).await?;
+			count += 1;
+		}
+		writer.flush().await?;
+		Ok(count)
+	}
+
+	/// reads the BSON documents that `export_bson` wrote and inserts them in batches like
+	/// `insert_many` , the documents keep their `_id` , returns the count of inserted documents
+	///
+	/// NOTE : created observer doesn't execute in this method
+	pub async fn import_bson(&self, reader: impl AsyncRead) -> MongodbResult<u64> {
+		let mut reader = std::pin::pin!(reader);
+		let mut batches = Batches::new(self.batching);
+		let mut offset = 0;
+		let mut result = None;
+		while let Some(document) = read_document(&mut reader).await? {
+			if let Some(batch) = batches.push(document)? {
+				offset += self.insert_batch(batch, offset, None, &mut result).await?;
+			}
+		}
+		if let Some(batch) = batches.finish() {
+			offset += self.insert_batch(batch, offset, None, &mut result).await?;
+		}
+		Ok(offset as u64)
+	}
+
+	/// inserts the last batch and returns the aggregated result
+	async fn insert_rest(
+		&self,
+		batches: Batches,
+		offset: usize,
+		options: Option<InsertManyOptions>,
+		mut result: Option<InsertManyResult>,
+	) -> MongodbResult<InsertManyResult> {
+		match batches.finish() {
+			Some(batch) => {
+				self.insert_batch(batch, offset, options, &mut result).await?;
+			}
+			// the driver reports an empty input as an error , so we pass it through
+			None if result.is_none() => {
+				self.insert_batch(Vec::new(), offset, options, &mut result).await?;
+			}
+			None => {}
+		}
+		result.ok_or_else(|| Error::new("None of the batches is inserted").into())
+	}
+
+	/// inserts one batch and merges its result , returns the count of the batch documents
+	async fn insert_batch(
+		&self,
+		batch: Vec<RawDocumentBuf>,
+		offset: usize,
+		options: Option<InsertManyOptions>,
+		result: &mut Option<InsertManyResult>,
+	) -> MongodbResult<usize> {
+		let count = batch.len();
+		let inserted =
+			self.collection.clone_with_type::<RawDocumentBuf>().insert_many(batch, options).await;
+		// a part of the documents can be inserted even when the insert fails
+		self.invalidate_cache();
+		let inserted = inserted.map_err(|error| offset_error(error, offset))?;
+		merge_result(result, inserted, offset);
+		Ok(count)
+	}
+
+	/// like `find_and_collect` but the read uses the session , like the snapshot session of
+	/// `Spark::read_snapshot`
+	pub async fn find_and_collect_with_session(
+		&self,
+		filter: impl Into<Document>,
+		options: impl Into<Option<FindOptions>>,
+		session: &mut ClientSession,
+	) -> MongodbResult<Vec<MongodbResult<M>>> {
+		let filter = self.find_filter(filter.into());
+		let mut cursor = self.collection.find_with_session(filter, options, session).await?;
+		Ok(cursor
+			.stream(session)
+			.map(|result| {
+				result.map(|mut inner| {
+					inner.after_load();
+					inner
 				})
-				.collect::<Vec<IndexModel>>();
-
-			for name in keys_to_remove {
-				let key = name.as_ref().unwrap();
-				let _ = coll
-					.drop_index(
-						key,
-						Some(DropIndexOptions::builder().max_time(max_time_to_drop).build()),
-					)
-					.await;
-			}
-			if !attrs.is_empty() {
-				let result = coll.create_indexes(attrs, None).await;
-				if let Err(error) = result {
-					error!("Can't create indexes : {:?}", error);
-				}
+			})
+			.collect()
+			.await)
+	}
+
+	/// reconciles the indexes of the collection with the attributes , the missing indexes are
+	/// created and the indexes of other fields are dropped , an index whose type changed
+	/// is dropped and created again with the new type
+	///
+	/// it returns when the indexes are built , so the queries after it can rely on them
+	pub async fn register_attributes(
+		&self,
+		attributes: Vec<(&str, IndexType)>,
+	) -> MongodbResult<()> {
+		self.register_indexes(attribute_specs(attributes)).await
+	}
+
+	/// like `register_attributes` but every spec can be unique , named or compound , the indexes
+	/// of the collection that aren't in `specs` are dropped
+	///
+	/// an index is kept as long as its keys and uniqueness are the same , so a changed name doesn't
+	/// rebuild it
+	pub async fn register_indexes(&self, specs: Vec<IndexSpec>) -> MongodbResult<()> {
+		reconcile_indexes(self.collection.clone_with_type(), specs).await
+	}
+
+	/// creates a TTL index on the date `field` , the server removes every document `expire_after`
+	/// after the date of the field , it's useful for collections like sessions or one time codes
+	///
+	/// the server silently ignores the documents whose field isn't a BSON date , so it's checked on
+	/// the serialized model when the field is set , the other indexes aren't touched and
+	/// `register_attributes` keeps this index
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// session_model.register_ttl("created_at", Duration::from_secs(30 * 60)).await?;
+	/// ```
+	pub async fn register_ttl(&self, field: &str, expire_after: Duration) -> MongodbResult<()> {
+		if let Some(value) = to_document(&*self.inner)?.get(field) {
+			if !matches!(value, Bson::DateTime(_)) {
+				return Err(Error::new(&format!(
+					"The TTL field {field} must be a date , but it's serialized as {:?}",
+					value.element_type()
+				))
+				.into());
 			}
-		};
+		}
+		let spec = IndexSpec::new(field, IndexType::Asc).expire_after(expire_after);
+		ensure_index(self.collection.clone_with_type(), spec).await
+	}
 
-		//TODO remove this thread , I think the channel is useless, review them
-		let task = tokio::spawn(register_attrs);
+	/// creates the text index of the fields for `search` , the `default_language` decides the stop
+	/// words and stemming , None is english
+	///
+	/// a collection can have only one text index , so an existing text index of other fields is
+	/// replaced , and `register_attributes` keeps it
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// product_model.register_text_index(vec!["name", "description"], None).await?;
+	/// ```
+	pub async fn register_text_index(
+		&self,
+		fields: Vec<&str>,
+		default_language: Option<&str>,
+	) -> MongodbResult<()> {
+		ensure_text_index(self.collection.clone_with_type(), fields, default_language).await
+	}
 
-		let wait_for_complete = async move {
-			let _ = task.await;
-			let _ = tx.send(());
-		};
+	/// creates a 2dsphere index on the GeoJSON `field` for `find_near` , the other indexes aren't
+	/// touched and `register_attributes` keeps this index
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// place_model.register_geo_index("location").await?;
+	/// ```
+	pub async fn register_geo_index(&self, field: &str) -> MongodbResult<()> {
+		let spec = IndexSpec::new(field, IndexType::Sphere2d);
+		ensure_index(self.collection.clone_with_type(), spec).await
+	}
 
-		tokio::task::spawn(wait_for_complete);
+	/// like `register_attributes` but the indexes are reconciled in a background task , the
+	/// errors are logged and also returned by the handle
+	pub fn register_attributes_detached(
+		&self,
+		attributes: Vec<(&str, IndexType)>,
+	) -> JoinHandle<MongodbResult<()>> {
+		let collection = self.collection.clone_with_type();
+		let specs = attribute_specs(attributes);
+		trace!("Spawn task to register indexes");
+		tokio::spawn(async move {
+			let result = reconcile_indexes(collection, specs).await;
+			if let Err(error) = &result {
+				error!("Can't register indexes : {error}");
+			}
+			result
+		})
 	}
 
 	pub async fn delete(
@@ -359,18 +2486,300 @@ where
 		query: impl Into<Document>,
 		options: impl Into<Option<DeleteOptions>>,
 	) -> MongodbResult<u64> {
-		let re = self.collection.delete_one(query.into(), options).await?.deleted_count;
+		// this must be pinned to handle recursive async call
+		Box::pin(M::deleting(self)).await?;
+		let query = self.scope_filter(query.into());
+		let options = options.into();
+		let re = self
+			.retrying("delete", Some(&query), is_retryable_write, || {
+				self.collection.delete_one(query.clone(), options.clone())
+			})
+			.await?
+			.deleted_count;
+
+		self.invalidate_cache();
+		if let Some(observer) = Spark::global_observer() {
+			observer.on_write(self.collection_name, WriteOp::Delete, &query);
+		}
 
 		// dispatch observer
+		let context = ObserverContext::deleted(self.inner.model_id(), query);
+		// this must be pinned to handle recursive async call
+		Box::pin(M::deleted_with_context(self, &context)).await?;
+
+		Ok(re)
+	}
+
+	/// like `delete` but the write uses the session , the global and `deleted` observers wait for
+	/// the commit and run by `dispatch_committed` like `save_with_session`
+	pub async fn delete_with_session(
+		&mut self,
+		query: impl Into<Document>,
+		options: impl Into<Option<DeleteOptions>>,
+		session: &mut ClientSession,
+	) -> MongodbResult<u64> {
+		// this must be pinned to handle recursive async call
+		Box::pin(M::deleting(self)).await?;
+		let query = self.scope_filter(query.into());
+		let re = self
+			.collection
+			.delete_one_with_session(query.clone(), options, session)
+			.await?
+			.deleted_count;
+
+		self.pending.push(Pending::Written(WriteOp::Delete, Box::new(query.clone())));
+		let context = ObserverContext::deleted(self.inner.model_id(), query);
+		self.pending.push(Pending::Deleted(Box::new(context)));
+		Ok(re)
+	}
+
+	/// deletes all documents of the query and returns the count of deleted documents
+	///
+	/// NOTE : deleted observer doesn't execute in this method because the deleted documents aren't
+	/// read , only the global observer is called once with the query
+	pub async fn delete_many(
+		&mut self,
+		query: impl Into<Document>,
+		options: impl Into<Option<DeleteOptions>>,
+	) -> MongodbResult<u64> {
+		let query = self.scope_filter(query.into());
+		let options = options.into();
+		let re = self
+			.retrying("delete_many", Some(&query), is_retryable_write, || {
+				self.collection.delete_many(query.clone(), options.clone())
+			})
+			.await?
+			.deleted_count;
+
+		self.invalidate_cache();
+		if let Some(observer) = Spark::global_observer() {
+			observer.on_write(self.collection_name, WriteOp::Delete, &query);
+		}
+
+		Ok(re)
+	}
+
+	/// deletes one document of the query atomically and returns it , the `deleted` observer is
+	/// dispatched with a model that holds the deleted document , the model itself is untouched
+	pub async fn find_one_and_delete(
+		&mut self,
+		query: impl Into<Document>,
+		options: impl Into<Option<FindOneAndDeleteOptions>>,
+	) -> MongodbResult<Option<M>> {
+		// the deleted document isn't known yet , so it's dispatched with this model
+		// this must be pinned to handle recursive async call
+		Box::pin(M::deleting(self)).await?;
+		let query = self.scope_filter(query.into());
+		let Some(mut inner) = self.collection.find_one_and_delete(query.clone(), options).await?
+		else {
+			return Ok(None);
+		};
+		inner.after_load();
+
+		self.invalidate_cache();
+		if let Some(observer) = Spark::global_observer() {
+			observer.on_write(self.collection_name, WriteOp::Delete, &query);
+		}
+
+		let mut deleted = self.with_inner(inner);
+		let context = ObserverContext::deleted(deleted.inner.model_id(), query);
+		// this must be pinned to handle recursive async call
+		Box::pin(M::deleted_with_context(&mut deleted, &context)).await?;
+		Ok(Some(deleted.take_inner()))
+	}
+
+	/// marks one document of the query as deleted by setting its `deleted_at` to now instead of
+	/// removing it , the `deleted` observer is dispatched like `delete` , it returns the count
+	/// of marked documents
+	///
+	/// a document that is already soft deleted isn't matched , so its `deleted_at` keeps the time
+	/// of the first delete
+	///
+	/// the model must be declared with `#[Model(coll_name = "users", soft_delete)]` , then the
+	/// reads skip the soft deleted documents unless the model is made `with_trashed`
+	///
+	/// # Examples
+	///
+	/// ```ignore
+	/// user_model.soft_delete(doc! { "name": "Hossein" }).await?;
+	/// let trashed = User::new_model(None).with_trashed().find_and_collect(doc! {}, None).await?;
+	/// ```
+	pub async fn soft_delete(&mut self, query: impl Into<Document>) -> MongodbResult<u64> {
+		let field = Self::soft_delete_field()?;
 		// this must be pinned to handle recursive async call
-		M::deleted(self).await?;
+		Box::pin(M::deleting(self)).await?;
+		let mut query = self.read_filter(query.into());
+		// a model made `with_trashed` must not mark the trashed documents again either
+		if !query.contains_key(field) {
+			query.insert(field, Bson::Null);
+		}
+		let re = self
+			.collection
+			.update_one(query.clone(), doc! { "$set": { field: DateTime::now() } }, None)
+			.await?
+			.modified_count;
+
+		// a document that is already trashed isn't deleted again , so nothing is notified
+		if re > 0 {
+			self.notify_write(WriteOp::Delete, &query);
+			let context = ObserverContext::deleted(self.inner.model_id(), query);
+			// this must be pinned to handle recursive async call
+			Box::pin(M::deleted_with_context(self, &context)).await?;
+		}
 
 		Ok(re)
 	}
 
+	/// clears the `deleted_at` of one soft deleted document of the query , it returns the count
+	/// of restored documents
+	pub async fn restore(&self, query: impl Into<Document>) -> MongodbResult<u64> {
+		let field = Self::soft_delete_field()?;
+		let mut query = self.scope_filter(query.into());
+		query.insert(field, doc! { "$ne": Bson::Null });
+		let re = self.collection.update_one(query, doc! { "$unset": { field: "" } }, None).await?;
+		self.invalidate_cache();
+		Ok(re.modified_count)
+	}
+
+	/// the model of a remembered document of `with_cache` , like it's loaded from database
+	#[cfg(feature = "cache")]
+	fn loaded(document: Document) -> MongodbResult<M> {
+		let mut inner: M = from_document(document)?;
+		inner.after_load();
+		Ok(inner)
+	}
+
+	/// clears the cache and notifies the global observer of a write
+	fn notify_write(&self, op: WriteOp, doc: &Document) {
+		self.invalidate_cache();
+		if let Some(observer) = Spark::global_observer() {
+			observer.on_write(self.collection_name, op, doc);
+		}
+	}
+
+	/// forgets the remembered reads of `with_cache` after a write
+	fn invalidate_cache(&self) {
+		#[cfg(feature = "cache")]
+		if let Some(cache) = &self.cache {
+			cache.clear();
+		}
+	}
+
+	fn soft_delete_field() -> MongodbResult<&'static str> {
+		M::deleted_at_field().ok_or_else(|| {
+			Error::new("The model doesn't use soft delete , add soft_delete to the Model macro")
+				.into()
+		})
+	}
+
 	pub fn fill(&mut self, inner: M) {
 		*self.inner = inner;
 	}
+
+	/// finds the documents of the scoped filter and runs `AfterLoad::after_load` on each of them
+	async fn find_models(
+		&self,
+		filter: Document,
+		options: Option<FindOptions>,
+	) -> MongodbResult<Vec<M>> {
+		let filter = self.read_filter(filter);
+		self.retrying("find", Some(&filter), is_retryable_read, || {
+			self.collection.find(filter.clone(), options.clone())
+		})
+		.await?
+		.map_ok(|mut inner| {
+			inner.after_load();
+			inner
+		})
+		.try_collect()
+		.await
+	}
+
+	/// the scoped filter of a find , an empty filter is passed as None
+	fn find_filter(&self, filter: Document) -> Option<Document> {
+		let converted = self.read_filter(filter);
+		if converted.is_empty() {
+			None
+		} else {
+			Some(converted)
+		}
+	}
+
+	/// derives the collection with the options of the current collection changed by `change` ,
+	/// so the concerns of the earlier calls are kept
+	fn with_collection_options(mut self, change: impl FnOnce(&mut CollectionOptions)) -> Self {
+		let mut options = CollectionOptions::builder()
+			.selection_criteria(self.collection.selection_criteria().cloned())
+			.read_concern(self.collection.read_concern().cloned())
+			.write_concern(self.collection.write_concern().cloned())
+			.build();
+		change(&mut options);
+		self.collection = self.db.collection_with_options(self.collection_name, options);
+		self
+	}
+
+	/// runs the database operation in its span , it runs again while the error is `retryable`
+	/// and the policy of `with_retry` allows another try
+	async fn retrying<T, F, Fut>(
+		&self,
+		operation: &'static str,
+		filter: Option<&Document>,
+		retryable: fn(&mongodb::error::Error) -> bool,
+		mut run: F,
+	) -> MongodbResult<T>
+	where
+		F: FnMut() -> Fut,
+		Fut: Future<Output = MongodbResult<T>>,
+	{
+		let mut attempts = Attempts::new(self.retry, self.collection_name, operation);
+		loop {
+			match Operation::start(self.collection_name, operation, filter).run(run()).await {
+				Err(error) if attempts.retry(&error, retryable).await => {}
+				result => return result,
+			}
+		}
+	}
+
+	/// makes a model of the same collection and scope that holds `inner`
+	fn with_inner(&self, inner: M) -> Model<'a, M> {
+		Model {
+			inner: Inner::new(inner),
+			db: self.db.clone(),
+			collection_name: self.collection_name,
+			collection: self.collection.clone(),
+			scope: self.scope.clone(),
+			batching: self.batching,
+			bulk_observers: self.bulk_observers,
+			with_trashed: self.with_trashed,
+			pending: Vec::new(),
+			retry: self.retry,
+			validation_order: self.validation_order,
+			#[cfg(feature = "cache")]
+			cache: self.cache.clone(),
+		}
+	}
+
+	/// the scoped filter of a read , the soft deleted documents are excluded unless the model is
+	/// `with_trashed` or the filter already has a condition on `deleted_at`
+	fn read_filter(&self, filter: Document) -> Document {
+		let mut filter = self.scope_filter(filter);
+		if let Some(field) = M::deleted_at_field() {
+			if !self.with_trashed && !filter.contains_key(field) {
+				// null matches both a missing field and an explicit null
+				filter.insert(field, Bson::Null);
+			}
+		}
+		filter
+	}
+
+	/// merges the tenant scope into the filter , the scope wins over a tenant field
+	/// that is already in the filter
+	fn scope_filter(&self, mut filter: Document) -> Document {
+		if let Some(scope) = &self.scope {
+			filter.extend(scope.clone());
+		}
+		filter
+	}
 }
 
 impl<'a, M> Model<'a, M>
@@ -393,36 +2802,120 @@ where
 	}
 
 	pub fn inner_to_doc(&self) -> MongodbResult<Document> {
-		let re = to_document(&self.inner)?;
+		let re = inner_document(&self.inner)?;
 		Ok(re)
 	}
 }
 
+fn attribute_specs(attributes: Vec<(&str, IndexType)>) -> Vec<IndexSpec> {
+	attributes.into_iter().map(|(attr, index_type)| IndexSpec::new(attr, index_type)).collect()
+}
+
+/// serializes the model that the inner holds
+fn inner_document<M: Serialize>(inner: &Inner<M>) -> std::result::Result<Document, ser::Error> {
+	to_document(&**inner)
+}
+
 // converts
 
-impl<'a, M> From<Model<'a, M>> for Document
+impl<'a, M> TryFrom<Model<'a, M>> for Document
 where
 	M: Serialize,
 {
-	fn from(value: Model<M>) -> Self {
-		mongodb::bson::to_document(&value.inner).unwrap()
+	type Error = ser::Error;
+
+	fn try_from(value: Model<M>) -> std::result::Result<Self, Self::Error> {
+		inner_document(&value.inner)
 	}
 }
 
-impl<'a, M> From<&Model<'a, M>> for Document
+impl<'a, M> TryFrom<&Model<'a, M>> for Document
 where
 	M: Serialize,
 {
-	fn from(value: &Model<'a, M>) -> Self {
-		mongodb::bson::to_document(&value.inner).unwrap()
+	type Error = ser::Error;
+
+	fn try_from(value: &Model<'a, M>) -> std::result::Result<Self, Self::Error> {
+		inner_document(&value.inner)
 	}
 }
 
-impl<'a, M> From<&mut Model<'a, M>> for Document
+impl<'a, M> TryFrom<&mut Model<'a, M>> for Document
 where
 	M: Serialize,
 {
-	fn from(value: &mut Model<'a, M>) -> Self {
-		mongodb::bson::to_document(&value.inner).unwrap()
+	type Error = ser::Error;
+
+	fn try_from(value: &mut Model<'a, M>) -> std::result::Result<Self, Self::Error> {
+		inner_document(&value.inner)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::model::util::ModelId;
+	use mongodb::Client;
+	use serde::Deserialize;
+
+	macro_rules! sized_model {
+		($name:ident, $padding:ty) => {
+			#[derive(Serialize, Deserialize, Default, Debug)]
+			struct $name {
+				padding: $padding,
+			}
+
+			impl ModelTimestamps for $name {}
+			impl Observer<$name> for $name {}
+			impl AfterLoad for $name {}
+			impl SoftDelete for $name {}
+			impl Validate for $name {}
+			impl ModelId for $name {
+				fn model_id(&self) -> Option<Bson> {
+					None
+				}
+			}
+		};
+	}
+
+	sized_model!(BelowThreshold, [[u8; 15]; 17]);
+	sized_model!(AtThreshold, [[u8; 32]; 8]);
+	sized_model!(AboveThreshold, ([[u8; 32]; 8], u8));
+
+	#[tokio::test]
+	async fn new_boxes_models_above_heap_threshold() {
+		assert_eq!(std::mem::size_of::<BelowThreshold>(), HEAP_THRESHOLD - 1);
+		assert_eq!(std::mem::size_of::<AtThreshold>(), HEAP_THRESHOLD);
+		assert_eq!(std::mem::size_of::<AboveThreshold>(), HEAP_THRESHOLD + 1);
+
+		// the client connects lazily , so no server is needed
+		let client = Client::with_uri_str("mongodb://localhost:27017").await.unwrap();
+		let db = Arc::new(client.database("heap_threshold"));
+
+		assert!(!Model::<BelowThreshold>::new(Some(&db), "below").inner.is_heap());
+		assert!(!Model::<AtThreshold>::new(Some(&db), "at").inner.is_heap());
+		assert!(Model::<AboveThreshold>::new(Some(&db), "above").inner.is_heap());
+	}
+
+	#[tokio::test]
+	async fn concerns_apply_only_to_the_derived_model() {
+		let client = Client::with_uri_str("mongodb://localhost:27017").await.unwrap();
+		let db = Arc::new(client.database("concerns"));
+
+		let critical = Model::<BelowThreshold>::new(Some(&db), "users")
+			.with_write_concern(WriteConcern::MAJORITY)
+			.with_read_preference(ReadPreference::Secondary {
+				options: Default::default(),
+			});
+		let default = Model::<BelowThreshold>::new(Some(&db), "users");
+
+		assert_eq!(critical.collection.write_concern(), Some(&WriteConcern::MAJORITY));
+		assert!(matches!(
+			critical.collection.selection_criteria(),
+			Some(SelectionCriteria::ReadPreference(ReadPreference::Secondary { .. }))
+		));
+		assert_eq!(default.collection.write_concern(), None);
+		assert_eq!(default.collection.selection_criteria(), None);
+		assert_eq!(db.write_concern(), None);
 	}
 }
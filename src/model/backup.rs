@@ -0,0 +1,46 @@
+use crate::error::Error;
+use crate::futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::model::MongodbResult;
+use mongodb::bson::RawDocumentBuf;
+
+/// the smallest valid BSON document is the 4 byte length and the trailing zero
+const MIN_DOCUMENT_BYTES: usize = 5;
+
+/// writes the document as is , a BSON document already starts with its length
+pub(crate) async fn write_document(
+	writer: &mut (impl AsyncWrite + Unpin),
+	document: &RawDocumentBuf,
+) -> MongodbResult<()> {
+	writer.write_all(document.as_bytes()).await?;
+	Ok(())
+}
+
+/// reads the next length prefixed document , returns None at the end of the input
+pub(crate) async fn read_document(
+	reader: &mut (impl AsyncRead + Unpin),
+) -> MongodbResult<Option<RawDocumentBuf>> {
+	let mut length = [0u8; 4];
+	let mut read = 0;
+	while read < length.len() {
+		let count = reader.read(&mut length[read..]).await?;
+		if count == 0 {
+			return if read == 0 {
+				Ok(None)
+			} else {
+				Err(Error::new("The input ends in the middle of a document length").into())
+			};
+		}
+		read += count;
+	}
+
+	let size = i32::from_le_bytes(length);
+	let size = usize::try_from(size)
+		.ok()
+		.filter(|size| *size >= MIN_DOCUMENT_BYTES)
+		.ok_or_else(|| Error::new(&format!("The input has an invalid document length {size}")))?;
+
+	let mut bytes = vec![0u8; size];
+	bytes[..4].copy_from_slice(&length);
+	reader.read_exact(&mut bytes[4..]).await?;
+	Ok(Some(RawDocumentBuf::from_bytes(bytes)?))
+}
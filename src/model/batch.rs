@@ -0,0 +1,129 @@
+use crate::error::Error;
+use crate::model::MongodbResult;
+use mongodb::bson::RawDocumentBuf;
+use mongodb::error::ErrorKind;
+use mongodb::results::InsertManyResult;
+
+/// the server rejects any document bigger than this
+const MAX_DOCUMENT_BYTES: usize = 16 * 1024 * 1024;
+
+/// the server accepts at most this many documents in a single write batch
+const MAX_BATCH_COUNT: usize = 100_000;
+
+/// the server limits a message to 48MB , we keep some room for the command envelope
+const MAX_BATCH_BYTES: usize = 48_000_000 - 16 * 1024;
+
+/// limits used by `insert_many` and `insert_stream` to split documents into
+/// several `insert_many` calls
+#[derive(Debug, Clone, Copy)]
+pub struct InsertBatching {
+	/// maximum number of documents in one call
+	pub max_count: usize,
+	/// maximum sum of the serialized sizes of the documents in one call
+	pub max_bytes: usize,
+}
+
+impl Default for InsertBatching {
+	fn default() -> Self {
+		InsertBatching {
+			max_count: MAX_BATCH_COUNT,
+			max_bytes: MAX_BATCH_BYTES,
+		}
+	}
+}
+
+/// collects serialized documents and hands out a batch as soon as the next document
+/// doesn't fit in the current one
+pub(crate) struct Batches<T = RawDocumentBuf> {
+	batching: InsertBatching,
+	current: Vec<T>,
+	bytes: usize,
+}
+
+impl Batches<RawDocumentBuf> {
+	/// adds the document and returns the previous batch if the document started a new one
+	pub(crate) fn push(
+		&mut self,
+		doc: RawDocumentBuf,
+	) -> MongodbResult<Option<Vec<RawDocumentBuf>>> {
+		let size = doc.as_bytes().len();
+		self.push_sized(doc, size)
+	}
+}
+
+impl<T> Batches<T> {
+	pub(crate) fn new(batching: InsertBatching) -> Self {
+		Batches {
+			batching,
+			current: Vec::new(),
+			bytes: 0,
+		}
+	}
+
+	/// adds the item whose serialized size is `size` and returns the previous batch
+	/// if the item started a new one
+	pub(crate) fn push_sized(&mut self, item: T, size: usize) -> MongodbResult<Option<Vec<T>>> {
+		if size > MAX_DOCUMENT_BYTES {
+			return Err(Error::new(&format!(
+				"The document is {size} bytes , the maximum document size is {MAX_DOCUMENT_BYTES} bytes"
+			))
+			.into());
+		}
+
+		let is_full = self.current.len() >= self.batching.max_count
+			|| self.bytes + size > self.batching.max_bytes;
+		let full = if !self.current.is_empty() && is_full {
+			self.bytes = 0;
+			Some(std::mem::take(&mut self.current))
+		} else {
+			None
+		};
+
+		self.bytes += size;
+		self.current.push(item);
+		Ok(full)
+	}
+
+	/// returns the last batch if it has any document
+	pub(crate) fn finish(self) -> Option<Vec<T>> {
+		if self.current.is_empty() {
+			None
+		} else {
+			Some(self.current)
+		}
+	}
+}
+
+/// merges the result of one batch into the aggregated result , `offset` is the index of
+/// the first document of the batch in the whole input
+pub(crate) fn merge_result(
+	aggregated: &mut Option<InsertManyResult>,
+	mut batch: InsertManyResult,
+	offset: usize,
+) {
+	let ids = std::mem::take(&mut batch.inserted_ids)
+		.into_iter()
+		.map(|(index, id)| (index + offset, id))
+		.collect();
+	match aggregated {
+		Some(result) => result.inserted_ids.extend(ids),
+		None => {
+			batch.inserted_ids = ids;
+			*aggregated = Some(batch);
+		}
+	}
+}
+
+/// the indexes in a bulk write error are relative to the batch , this moves them to
+/// the index of the document in the whole input
+pub(crate) fn offset_error(
+	mut error: mongodb::error::Error,
+	offset: usize,
+) -> mongodb::error::Error {
+	if let ErrorKind::BulkWrite(failure) = &mut *error.kind {
+		failure.write_errors.iter_mut().flatten().for_each(|write_error| {
+			write_error.index += offset;
+		});
+	}
+	error
+}
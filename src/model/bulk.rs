@@ -0,0 +1,166 @@
+use crate::error::Error;
+use crate::model::batch::InsertBatching;
+use crate::model::MongodbResult;
+use mongodb::bson::{from_document, Bson, Document};
+use mongodb::error::{BulkWriteFailure, ErrorKind};
+use mongodb::Database;
+
+/// the server accepts at most this many statements in a single write command
+const MAX_WRITE_BATCH_COUNT: usize = 100_000;
+
+/// a command document is limited to 16MB , we keep some room for the rest of the command
+const MAX_COMMAND_BYTES: usize = 16 * 1024 * 1024 - 16 * 1024;
+
+/// One write of `Model::bulk_write`
+#[derive(Debug, Clone)]
+pub enum WriteOp<M> {
+	/// inserts the model , the timestamps are set and an `_id` is made if it has none
+	Insert(M),
+	/// applies the update to the first document of the filter , the update must only use
+	/// update operators like `$set`
+	UpdateOne {
+		filter: Document,
+		update: Document,
+	},
+	/// deletes the first document of the filter
+	DeleteOne(Document),
+}
+
+/// The options of `Model::bulk_write`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkWriteOptions {
+	/// an ordered bulk write runs the writes in order and stops at the first failed one ,
+	/// an unordered one groups the writes by kind and runs all of them even if some fail
+	pub ordered: bool,
+}
+
+impl Default for BulkWriteOptions {
+	fn default() -> Self {
+		BulkWriteOptions {
+			ordered: true,
+		}
+	}
+}
+
+impl BulkWriteOptions {
+	pub fn unordered() -> Self {
+		BulkWriteOptions {
+			ordered: false,
+		}
+	}
+}
+
+/// The counts of a bulk write
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BulkWriteResult {
+	/// the count of inserted documents
+	pub inserted_count: u64,
+	/// the count of documents that matched the update filters
+	pub matched_count: u64,
+	/// the count of documents that were changed by the updates
+	pub modified_count: u64,
+	/// the count of deleted documents
+	pub deleted_count: u64,
+}
+
+/// the kind of write command that runs a statement , the order is the order of the
+/// commands of an unordered bulk write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum WriteKind {
+	Insert,
+	Update,
+	Delete,
+}
+
+impl WriteKind {
+	/// the command name and the field of its statements
+	pub(crate) fn command(self) -> (&'static str, &'static str) {
+		match self {
+			WriteKind::Insert => ("insert", "documents"),
+			WriteKind::Update => ("update", "updates"),
+			WriteKind::Delete => ("delete", "deletes"),
+		}
+	}
+
+	/// adds the counts of a response of the command to the result
+	pub(crate) fn add_counts(self, response: &Document, result: &mut BulkWriteResult) {
+		match self {
+			WriteKind::Insert => result.inserted_count += response_count(response, "n"),
+			WriteKind::Update => {
+				result.matched_count += response_count(response, "n");
+				result.modified_count += response_count(response, "nModified");
+			}
+			WriteKind::Delete => result.deleted_count += response_count(response, "n"),
+		}
+	}
+}
+
+/// the limits of the statements in one write command
+pub(crate) fn command_batching() -> InsertBatching {
+	InsertBatching {
+		max_count: MAX_WRITE_BATCH_COUNT,
+		max_bytes: MAX_COMMAND_BYTES,
+	}
+}
+
+/// checks that the update only has operators like `$set` , in a write command an update
+/// without operators replaces the whole document
+pub(crate) fn check_update_operators(index: usize, update: &Document) -> MongodbResult<()> {
+	if update.is_empty() || update.keys().any(|key| !key.starts_with('$')) {
+		return Err(Error::new(&format!(
+			"The update at index {index} must only use update operators like $set"
+		))
+		.into());
+	}
+	Ok(())
+}
+
+/// runs a write command , a write command reports its failed statements in a successful response
+/// so they are returned as a bulk write error here
+pub(crate) async fn run_write_command(db: &Database, command: Document) -> MongodbResult<Document> {
+	let response = db.run_command(command, None).await?;
+	if response.contains_key("writeErrors") || response.contains_key("writeConcernError") {
+		let failure = from_document::<BulkWriteFailure>(response)?;
+		return Err(ErrorKind::BulkWrite(failure).into());
+	}
+	Ok(response)
+}
+
+/// the indexes in a bulk write error are relative to the command , this moves them to
+/// the index of the operation in the whole input
+pub(crate) fn reindex_error(
+	mut error: mongodb::error::Error,
+	indexes: &[usize],
+) -> mongodb::error::Error {
+	if let ErrorKind::BulkWrite(failure) = &mut *error.kind {
+		failure.write_errors.iter_mut().flatten().for_each(|write_error| {
+			if let Some(index) = indexes.get(write_error.index) {
+				write_error.index = *index;
+			}
+		});
+	}
+	error
+}
+
+/// adds the write errors of a later command to the first failure of an unordered bulk write
+pub(crate) fn merge_failure(first: &mut mongodb::error::Error, error: mongodb::error::Error) {
+	if let (ErrorKind::BulkWrite(failure), ErrorKind::BulkWrite(other)) =
+		(&mut *first.kind, *error.kind)
+	{
+		if let Some(write_errors) = other.write_errors {
+			failure.write_errors.get_or_insert_with(Vec::new).extend(write_errors);
+		}
+		if failure.write_concern_error.is_none() {
+			failure.write_concern_error = other.write_concern_error;
+		}
+	}
+}
+
+/// reads a count of a write command response
+pub(crate) fn response_count(response: &Document, key: &str) -> u64 {
+	match response.get(key) {
+		Some(Bson::Int32(count)) => *count as u64,
+		Some(Bson::Int64(count)) => *count as u64,
+		_ => 0,
+	}
+}
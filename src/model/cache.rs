@@ -0,0 +1,134 @@
+use mongodb::bson::Document;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The remembered reads of a model made by `Model::with_cache` , the documents are kept by the
+/// read , its filter and its options , until the ttl passes or the model writes
+#[derive(Debug)]
+pub(crate) struct QueryCache {
+	ttl: Duration,
+	// the reads are grouped by the hash of their key , a group has more than one read only when
+	// their hashes collide
+	entries: Mutex<HashMap<u64, Vec<Entry>>>,
+}
+
+/// A read of `QueryCache` , the filters that only differ in the order of their fields are
+/// different reads
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CacheKey {
+	operation: &'static str,
+	filter: Document,
+	options: String,
+	hash: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+	key: CacheKey,
+	stored: Instant,
+	documents: Vec<Document>,
+}
+
+impl QueryCache {
+	pub(crate) fn new(ttl: Duration) -> Self {
+		QueryCache {
+			ttl,
+			entries: Mutex::default(),
+		}
+	}
+
+	pub(crate) fn key(
+		operation: &'static str,
+		filter: &Document,
+		options: &impl Debug,
+	) -> CacheKey {
+		let options = format!("{options:?}");
+		let mut hasher = DefaultHasher::new();
+		operation.hash(&mut hasher);
+		filter.to_string().hash(&mut hasher);
+		options.hash(&mut hasher);
+		CacheKey {
+			operation,
+			filter: filter.clone(),
+			options,
+			hash: hasher.finish(),
+		}
+	}
+
+	/// the documents of the read if they are younger than the ttl
+	pub(crate) fn get(&self, key: &CacheKey) -> Option<Vec<Document>> {
+		let entries = self.entries();
+		let entry = entries.get(&key.hash)?.iter().find(|entry| entry.key == *key)?;
+		(entry.stored.elapsed() < self.ttl).then(|| entry.documents.clone())
+	}
+
+	/// remembers the documents of the read , the expired reads are dropped first so the reads
+	/// that are never repeated don't stay in memory
+	pub(crate) fn insert(&self, key: CacheKey, documents: Vec<Document>) {
+		let mut entries = self.entries();
+		entries.retain(|_, group| {
+			group.retain(|entry| entry.stored.elapsed() < self.ttl && entry.key != key);
+			!group.is_empty()
+		});
+		entries.entry(key.hash).or_default().push(Entry {
+			key,
+			stored: Instant::now(),
+			documents,
+		});
+	}
+
+	/// forgets all of the reads , a write can change the result of any of them
+	pub(crate) fn clear(&self) {
+		self.entries().clear();
+	}
+
+	fn entries(&self) -> MutexGuard<'_, HashMap<u64, Vec<Entry>>> {
+		// a poisoned lock only means another thread panicked while it used the map
+		self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use mongodb::bson::doc;
+
+	#[test]
+	fn reads_expire_and_are_cleared() {
+		let cache = QueryCache::new(Duration::from_secs(60));
+		let key = QueryCache::key("find", &doc! { "code": "IR" }, &None::<()>);
+		assert_ne!(key, QueryCache::key("find_one", &doc! { "code": "IR" }, &None::<()>));
+		assert_eq!(cache.get(&key), None);
+
+		cache.insert(key.clone(), vec![doc! { "code": "IR" }]);
+		assert_eq!(cache.get(&key), Some(vec![doc! { "code": "IR" }]));
+		cache.clear();
+		assert_eq!(cache.get(&key), None);
+
+		let expired = QueryCache::new(Duration::ZERO);
+		expired.insert(key.clone(), vec![doc! { "code": "IR" }]);
+		assert_eq!(expired.get(&key), None);
+		// the expired read is dropped by the next insert
+		let other = QueryCache::key("find", &doc! { "code": "DE" }, &None::<()>);
+		expired.insert(other, vec![]);
+		assert_eq!(expired.entries().values().map(Vec::len).sum::<usize>(), 1);
+	}
+
+	#[test]
+	fn colliding_hashes_keep_their_own_reads() {
+		let cache = QueryCache::new(Duration::from_secs(60));
+		let key = QueryCache::key("find", &doc! { "code": "IR" }, &None::<()>);
+		let mut colliding = QueryCache::key("find", &doc! { "code": "DE" }, &None::<()>);
+		colliding.hash = key.hash;
+
+		cache.insert(key.clone(), vec![doc! { "code": "IR" }]);
+		assert_eq!(cache.get(&colliding), None);
+		cache.insert(colliding.clone(), vec![doc! { "code": "DE" }]);
+		assert_eq!(cache.get(&key), Some(vec![doc! { "code": "IR" }]));
+		assert_eq!(cache.get(&colliding), Some(vec![doc! { "code": "DE" }]));
+	}
+}
@@ -0,0 +1,387 @@
+use crate::futures::TryStreamExt;
+use crate::model::retry::error_code;
+use crate::model::MongodbResult;
+use mongodb::bson::{to_document, Bson, Document};
+use mongodb::options::{
+	Collation, CollationStrength, DropIndexOptions, IndexOptions, ListIndexesOptions,
+};
+use mongodb::{Collection, IndexModel};
+use std::time::Duration;
+
+/// the server error code of a collection that doesn't exist yet
+const NAMESPACE_NOT_FOUND_CODE: i32 = 26;
+
+/// the server error code of dropping an index that doesn't exist
+const INDEX_NOT_FOUND_CODE: i32 = 27;
+
+/// the time that the server can spend on listing or dropping the indexes
+const MAX_INDEX_TIME: Duration = Duration::from_secs(5);
+
+/// The type of an index key , it's the value of the field in the index keys document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexType {
+	/// `{ field: 1 }`
+	Asc,
+	/// `{ field: -1 }` , useful when queries sort descending on the field
+	Desc,
+	/// `{ field: "hashed" }` , useful for equality matches and hashed sharding
+	Hashed,
+	/// `{ field: "2dsphere" }` , for the GeoJSON fields of the queries like `$near`
+	Sphere2d,
+}
+
+impl IndexType {
+	/// the value of the field in the index keys document
+	pub(crate) fn key_value(&self) -> Bson {
+		match self {
+			IndexType::Asc => Bson::Int32(1),
+			IndexType::Desc => Bson::Int32(-1),
+			IndexType::Hashed => Bson::String("hashed".to_string()),
+			IndexType::Sphere2d => Bson::String("2dsphere".to_string()),
+		}
+	}
+
+	/// checks the value of a field in the keys document of an existing index , the server can
+	/// report the direction as any numeric type
+	pub(crate) fn matches(&self, value: &Bson) -> bool {
+		let direction = match value {
+			Bson::Int32(direction) => Some(*direction as f64),
+			Bson::Int64(direction) => Some(*direction as f64),
+			Bson::Double(direction) => Some(*direction),
+			_ => None,
+		};
+		match self {
+			IndexType::Asc => direction.is_some_and(|direction| direction > 0.0),
+			IndexType::Desc => direction.is_some_and(|direction| direction < 0.0),
+			IndexType::Hashed => value.as_str() == Some("hashed"),
+			IndexType::Sphere2d => value.as_str() == Some("2dsphere"),
+		}
+	}
+}
+
+/// the collation that compares the strings without the case , `John@Mail.com` equals
+/// `john@mail.com` , it's the `en` locale with the secondary strength
+pub fn case_insensitive() -> Collation {
+	Collation::builder().locale("en").strength(CollationStrength::Secondary).build()
+}
+
+/// An index of `Model::register_indexes` , it has one key or several keys in order for
+/// a compound index
+/// ```ignore
+/// user_model
+///     .register_indexes(vec![
+///         IndexSpec::new("email", IndexType::Asc).unique().collation(case_insensitive()),
+///         IndexSpec::compound(vec![("last", IndexType::Asc), ("age", IndexType::Desc)]),
+///     ])
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct IndexSpec {
+	/// the fields of the index in order with their type
+	pub keys: Vec<(String, IndexType)>,
+	pub unique: bool,
+	/// the name of the index , when it's None the server makes it from the keys
+	pub name: Option<String>,
+	/// the documents are removed this long after the date of the key , it's a TTL index
+	pub expire_after: Option<Duration>,
+	/// how the strings of the keys are compared , None is the binary comparison
+	pub collation: Option<Collation>,
+}
+
+impl PartialEq for IndexSpec {
+	fn eq(&self, other: &Self) -> bool {
+		self.keys == other.keys
+			&& self.unique == other.unique
+			&& self.name == other.name
+			&& self.expire_after == other.expire_after
+			&& collation_document(self.collation.as_ref())
+				== collation_document(other.collation.as_ref())
+	}
+}
+
+impl Eq for IndexSpec {}
+
+impl IndexSpec {
+	/// an index on one field
+	pub fn new(field: &str, index_type: IndexType) -> Self {
+		Self::compound(vec![(field, index_type)])
+	}
+
+	/// an index on several fields , the order of the fields matters for the queries that use it
+	pub fn compound(keys: Vec<(&str, IndexType)>) -> Self {
+		IndexSpec {
+			keys: keys
+				.into_iter()
+				.map(|(field, index_type)| (field.to_string(), index_type))
+				.collect(),
+			unique: false,
+			name: None,
+			expire_after: None,
+			collation: None,
+		}
+	}
+
+	/// rejects the documents that have the same value of the keys as another document
+	pub fn unique(mut self) -> Self {
+		self.unique = true;
+		self
+	}
+
+	pub fn name(mut self, name: &str) -> Self {
+		self.name = Some(name.to_string());
+		self
+	}
+
+	/// makes it a TTL index , the key must be a single date field
+	pub fn expire_after(mut self, expire_after: Duration) -> Self {
+		self.expire_after = Some(expire_after);
+		self
+	}
+
+	/// the collation of the keys , like `case_insensitive()` for the emails
+	///
+	/// a unique index with the secondary strength , the strength 2 , is what enforces the case
+	/// insensitive uniqueness , the queries use the index only when they have the same collation
+	pub fn collation(mut self, collation: Collation) -> Self {
+		self.collation = Some(collation);
+		self
+	}
+
+	/// checks that an existing index has the same keys in the same order , the same uniqueness ,
+	/// the same expiry and the same collation , the other options like the name don't matter
+	fn matches(&self, index: &IndexModel) -> bool {
+		let options = index.options.as_ref();
+		let unique = options.and_then(|options| options.unique).unwrap_or(false);
+		let expire_after = options.and_then(|options| options.expire_after);
+		unique == self.unique
+			&& expire_after.map(|expire| expire.as_secs())
+				== self.expire_after.map(|expire| expire.as_secs())
+			&& self.collation_matches(options.and_then(|options| options.collation.as_ref()))
+			&& self.has_keys_of(index)
+	}
+
+	/// the server reports every option of the collation with its default , so only the options
+	/// that the spec sets are compared
+	fn collation_matches(&self, existing: Option<&Collation>) -> bool {
+		let existing = collation_document(existing);
+		match &self.collation {
+			// an index without collation compares binary like the simple locale
+			None => existing.is_empty() || existing.get_str("locale") == Ok("simple"),
+			Some(collation) => collation_document(Some(collation))
+				.iter()
+				.all(|(key, value)| existing.get(key) == Some(value)),
+		}
+	}
+
+	fn has_keys_of(&self, index: &IndexModel) -> bool {
+		index.keys.len() == self.keys.len()
+			&& index.keys.iter().zip(&self.keys).all(|((key, value), (field, index_type))| {
+				key == field && index_type.matches(value)
+			})
+	}
+
+	fn index_model(self) -> IndexModel {
+		let keys = self
+			.keys
+			.into_iter()
+			.map(|(field, index_type)| (field, index_type.key_value()))
+			.collect::<Document>();
+		let options = IndexOptions::builder()
+			.unique(self.unique.then_some(true))
+			.name(self.name)
+			.expire_after(self.expire_after)
+			.collation(self.collation)
+			.build();
+		IndexModel::builder().keys(keys).options(options).build()
+	}
+}
+
+/// reconciles the indexes of the collection with the specs , the missing indexes are created and
+/// the other indexes are dropped , an index whose keys , uniqueness or expiry changed is dropped and
+/// created again
+///
+/// a TTL index is only dropped when a spec has its keys , so the index of `Model::register_ttl`
+/// survives the reconciliation of `Model::register_attributes` , the same goes for the 2dsphere
+/// index of `Model::register_geo_index` , the text index of `Model::register_text_index` is never
+/// dropped because a collection has only one of them
+pub(crate) async fn reconcile_indexes(
+	collection: Collection<Document>,
+	mut specs: Vec<IndexSpec>,
+) -> MongodbResult<()> {
+	let existing = list_indexes(&collection).await?;
+
+	let mut names_to_drop = Vec::new();
+	for index_model in existing.iter().filter(|index| !index.keys.contains_key("_id")) {
+		match specs.iter().position(|spec| spec.matches(index_model)) {
+			// means the index exists in struct and database and not need to create it
+			Some(pos) => {
+				specs.remove(pos);
+			}
+			None if (is_ttl(index_model) || is_geo(index_model))
+				&& !specs.iter().any(|spec| spec.has_keys_of(index_model)) => {}
+			None if is_text(index_model) => {}
+			// means the index must remove because not exists in struct
+			// or it's changed , then it's created again
+			None => {
+				if let Some(name) = index_name(index_model) {
+					names_to_drop.push(name);
+				}
+			}
+		}
+	}
+
+	for name in names_to_drop {
+		drop_index(&collection, name).await?;
+	}
+
+	if !specs.is_empty() {
+		let index_models =
+			specs.into_iter().map(IndexSpec::index_model).collect::<Vec<IndexModel>>();
+		collection.create_indexes(index_models, None).await?;
+	}
+	Ok(())
+}
+
+/// creates the index of the spec without touching the other indexes , an index with the same keys
+/// but other options is dropped first
+pub(crate) async fn ensure_index(
+	collection: Collection<Document>,
+	spec: IndexSpec,
+) -> MongodbResult<()> {
+	let existing = list_indexes(&collection).await?;
+	if existing.iter().any(|index| spec.matches(index)) {
+		return Ok(());
+	}
+	if let Some(name) = existing.iter().find(|index| spec.has_keys_of(index)).and_then(index_name) {
+		drop_index(&collection, name).await?;
+	}
+	collection.create_index(spec.index_model(), None).await?;
+	Ok(())
+}
+
+/// creates the text index of the fields , a collection can have only one text index so an existing
+/// text index of other fields or another language is dropped first
+pub(crate) async fn ensure_text_index(
+	collection: Collection<Document>,
+	fields: Vec<&str>,
+	default_language: Option<&str>,
+) -> MongodbResult<()> {
+	let existing = list_indexes(&collection).await?;
+	if let Some(text_index) = existing.iter().find(|index| is_text(index)) {
+		let options = text_index.options.as_ref();
+		// the server reports the fields of a text index in its weights , not in its keys
+		let weights = options.and_then(|options| options.weights.as_ref());
+		let same_fields = weights.is_some_and(|weights| {
+			weights.len() == fields.len() && fields.iter().all(|field| weights.contains_key(field))
+		});
+		let language = options.and_then(|options| options.default_language.as_deref());
+		// the server default language is english
+		if same_fields && language.unwrap_or("english") == default_language.unwrap_or("english") {
+			return Ok(());
+		}
+		if let Some(name) = index_name(text_index) {
+			drop_index(&collection, name).await?;
+		}
+	}
+
+	let keys =
+		fields.iter().map(|field| (field.to_string(), Bson::from("text"))).collect::<Document>();
+	let options =
+		IndexOptions::builder().default_language(default_language.map(str::to_string)).build();
+	collection
+		.create_index(IndexModel::builder().keys(keys).options(options).build(), None)
+		.await?;
+	Ok(())
+}
+
+async fn list_indexes(collection: &Collection<Document>) -> MongodbResult<Vec<IndexModel>> {
+	let list_options = ListIndexesOptions::builder().max_time(MAX_INDEX_TIME).build();
+	match collection.list_indexes(list_options).await {
+		Ok(indexes) => indexes.try_collect().await,
+		// a collection that doesn't exist yet has no indexes
+		Err(error) if error_code(&error) == Some(NAMESPACE_NOT_FOUND_CODE) => Ok(Vec::new()),
+		Err(error) => Err(error),
+	}
+}
+
+async fn drop_index(collection: &Collection<Document>, name: String) -> MongodbResult<()> {
+	let drop_options = DropIndexOptions::builder().max_time(MAX_INDEX_TIME).build();
+	match collection.drop_index(name, drop_options).await {
+		// it's already dropped by someone else
+		Err(error) if error_code(&error) == Some(INDEX_NOT_FOUND_CODE) => Ok(()),
+		result => result,
+	}
+}
+
+fn collation_document(collation: Option<&Collation>) -> Document {
+	collation.and_then(|collation| to_document(collation).ok()).unwrap_or_default()
+}
+
+fn index_name(index: &IndexModel) -> Option<String> {
+	index.options.as_ref().and_then(|options| options.name.clone())
+}
+
+fn is_geo(index: &IndexModel) -> bool {
+	index.keys.values().any(|value| IndexType::Sphere2d.matches(value))
+}
+
+fn is_text(index: &IndexModel) -> bool {
+	index.keys.contains_key("_fts")
+}
+
+fn is_ttl(index: &IndexModel) -> bool {
+	index.options.as_ref().is_some_and(|options| options.expire_after.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use mongodb::bson::doc;
+
+	fn existing(collation: Option<Collation>) -> IndexModel {
+		let options = IndexOptions::builder().collation(collation).build();
+		IndexModel::builder().keys(doc! { "email": 1 }).options(options).build()
+	}
+
+	#[test]
+	fn collation_is_compared_by_the_options_of_the_spec() {
+		// the server reports the defaults of every option
+		let reported = Collation::builder()
+			.locale("en")
+			.strength(CollationStrength::Secondary)
+			.case_level(false)
+			.numeric_ordering(false)
+			.backwards(false)
+			.build();
+		let spec = IndexSpec::new("email", IndexType::Asc).collation(case_insensitive());
+		assert!(spec.matches(&existing(Some(reported))));
+
+		let tertiary = Collation::builder().locale("en").build();
+		assert!(!spec.matches(&existing(Some(tertiary))));
+		assert!(!spec.matches(&existing(None)));
+	}
+
+	#[test]
+	fn text_index_is_recognized_by_its_server_keys() {
+		// the server reports the keys of a text index as _fts and _ftsx
+		let text = IndexModel::builder().keys(doc! { "_fts": "text", "_ftsx": 1 }).build();
+		assert!(is_text(&text));
+		assert!(!is_text(&existing(None)));
+	}
+
+	#[test]
+	fn geo_index_is_recognized() {
+		let geo = IndexSpec::new("location", IndexType::Sphere2d).index_model();
+		assert!(is_geo(&geo));
+		assert!(IndexSpec::new("location", IndexType::Sphere2d).matches(&geo));
+		assert!(!is_geo(&existing(None)));
+	}
+
+	#[test]
+	fn spec_without_collation_matches_only_binary_indexes() {
+		let spec = IndexSpec::new("email", IndexType::Asc);
+		assert!(spec.matches(&existing(None)));
+		assert!(spec.matches(&existing(Some(Collation::builder().locale("simple").build()))));
+		assert!(!spec.matches(&existing(Some(case_insensitive()))));
+	}
+}
@@ -0,0 +1,90 @@
+use crate::model::MongodbResult;
+use mongodb::bson::Document;
+use std::future::Future;
+
+/// A database operation of a model , with the `tracing` feature it runs in a `spark_orm` span that
+/// records the collection , the operation and the elapsed milliseconds , and a failure is an error
+/// event of the span , without the feature it only runs the operation
+///
+/// the filter is recorded only when the subscriber is interested in the debug level , a read that
+/// `Model::with_cache` answers from memory has its own span whose `cache` is `hit`
+pub(crate) struct Operation {
+	#[cfg(feature = "tracing")]
+	span: tracing::Span,
+}
+
+impl Operation {
+	#[cfg(feature = "tracing")]
+	pub(crate) fn start(
+		collection: &str,
+		operation: &'static str,
+		filter: Option<&Document>,
+	) -> Self {
+		let span = tracing::info_span!(
+			"spark_orm",
+			collection,
+			operation,
+			filter = tracing::field::Empty,
+			elapsed_ms = tracing::field::Empty,
+			cache = tracing::field::Empty,
+		);
+		if let Some(filter) = filter {
+			if tracing::enabled!(tracing::Level::DEBUG) {
+				span.record("filter", tracing::field::display(filter));
+			}
+		}
+		Operation {
+			span,
+		}
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	pub(crate) fn start(
+		_collection: &str,
+		_operation: &'static str,
+		_filter: Option<&Document>,
+	) -> Self {
+		Operation {}
+	}
+
+	/// records a read that is answered by the cache , no database operation runs for it
+	#[cfg(all(feature = "cache", feature = "tracing"))]
+	pub(crate) fn cache_hit(collection: &str, operation: &'static str, filter: Option<&Document>) {
+		let operation = Self::start(collection, operation, filter);
+		operation.span.record("cache", "hit");
+		operation.span.record("elapsed_ms", 0u64);
+		tracing::debug!(parent: &operation.span, "The read is answered from the cache");
+	}
+
+	#[cfg(all(feature = "cache", not(feature = "tracing")))]
+	pub(crate) fn cache_hit(
+		_collection: &str,
+		_operation: &'static str,
+		_filter: Option<&Document>,
+	) {
+	}
+
+	#[cfg(feature = "tracing")]
+	pub(crate) async fn run<T>(
+		self,
+		future: impl Future<Output = MongodbResult<T>>,
+	) -> MongodbResult<T> {
+		use tracing::Instrument;
+
+		let started = std::time::Instant::now();
+		let result = future.instrument(self.span.clone()).await;
+		self.span.record("elapsed_ms", started.elapsed().as_millis() as u64);
+		if let Err(error) = &result {
+			tracing::error!(parent: &self.span, %error, "The database operation failed");
+		}
+		result
+	}
+
+	#[cfg(not(feature = "tracing"))]
+	pub(crate) async fn run<T>(
+		self,
+		future: impl Future<Output = MongodbResult<T>>,
+	) -> MongodbResult<T> {
+		future.await
+	}
+}
@@ -0,0 +1,70 @@
+use crate::error::Error;
+use crate::futures::future::BoxFuture;
+use crate::model::MongodbResult;
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::Database;
+use std::collections::HashSet;
+
+/// the collection that keeps the names of the applied migrations
+pub const MIGRATIONS_COLLECTION: &str = "_rspark_migrations";
+
+/// One change of the data , like a backfill of a new field , it's run once by `Spark::run_migrations`
+/// ```ignore
+/// struct BackfillStatus;
+///
+/// impl Migration for BackfillStatus {
+///     fn name(&self) -> &str {
+///         "2024_05_01_backfill_status"
+///     }
+///
+///     fn up<'a>(&'a self, db: &'a Database) -> BoxFuture<'a, MongodbResult<()>> {
+///         async move {
+///             db.collection::<Document>("users")
+///                 .update_many(doc! { "status": { "$exists": false } }, doc! { "$set": { "status": "active" } }, None)
+///                 .await?;
+///             Ok(())
+///         }
+///         .boxed()
+///     }
+/// }
+/// ```
+pub trait Migration: Send + Sync {
+	/// the unique name of the migration , it's what is stored when the migration is applied ,
+	/// so it must not change after the migration has run
+	fn name(&self) -> &str;
+
+	fn up<'a>(&'a self, db: &'a Database) -> BoxFuture<'a, MongodbResult<()>>;
+}
+
+/// runs the migrations that aren't applied yet in their order and records each one after it
+/// succeeded , returns the names of the applied migrations
+///
+/// it stops at the first failed migration , so the later ones stay pending and run with the
+/// next call , a migration that fails after a part of its writes should be safe to run again
+pub async fn run_migrations(
+	db: &Database,
+	migrations: Vec<Box<dyn Migration>>,
+) -> MongodbResult<Vec<String>> {
+	let mut names = HashSet::new();
+	if let Some(migration) = migrations.iter().find(|migration| !names.insert(migration.name())) {
+		return Err(Error::new(&format!(
+			"The migration {} is given more than once , the names must be unique",
+			migration.name()
+		))
+		.into());
+	}
+
+	let collection = db.collection::<Document>(MIGRATIONS_COLLECTION);
+	let applied = collection.distinct("_id", None, None).await?;
+	let mut ran = Vec::new();
+	for migration in migrations {
+		let name = migration.name();
+		if applied.iter().any(|id| id.as_str() == Some(name)) {
+			continue;
+		}
+		migration.up(db).await?;
+		collection.insert_one(doc! { "_id": name, "applied_at": DateTime::now() }, None).await?;
+		ran.push(name.to_string());
+	}
+	Ok(ran)
+}
@@ -1,18 +1,69 @@
+use crate::model::bulk::BulkWriteResult;
 use crate::model::{Model, MongodbResult};
+use mongodb::bson::{Bson, Document};
 
 /// This trait implement by default for Model
 /// but if user wants to override and use it must tell to Model macro
-/// ```rust
+/// ```ignore
 /// #[Model(coll_name = "users", observer)]
 /// #[derive(Serialize, Deserialize, Debug, Default)]
 /// struct User {
 ///    name: String,
 /// }
 /// ```
-
 #[allow(async_fn_in_trait)]
 #[allow(unused)]
 pub trait Observer<M> {
+    /// this call first in save , before it decides to insert or update
+    /// the changes to the model are written to database , an error aborts the save
+    #[inline]
+    async fn saving(model: &mut Model<'_, M>) -> MongodbResult<()> {
+        Ok(())
+    }
+
+    /// this call before a new document is inserted by save
+    /// the changes to the model are written to database , an error aborts the save
+    #[inline]
+    async fn creating(model: &mut Model<'_, M>) -> MongodbResult<()> {
+        Ok(())
+    }
+
+    /// this call before an existing document is updated by save or update
+    /// for save the changes to the model are written to database , an error aborts the write
+    #[inline]
+    async fn updating(model: &mut Model<'_, M>) -> MongodbResult<()> {
+        Ok(())
+    }
+
+    /// this is what is called before `update` and `update_with_session` write , it gets the filter
+    /// and the update document , a change to the update is written , an error aborts the update ,
+    /// by default it calls `updating`
+    /// ```ignore
+    /// impl Observer<User> for User {
+    ///     async fn updating_with_query(
+    ///         model: &mut Model<'_, User>,
+    ///         query: &Document,
+    ///         update: &mut Document,
+    ///     ) -> MongodbResult<()> {
+    ///         if let Ok(set) = update.get_document_mut("$set") {
+    ///             if let Ok(password) = set.get_str("password") {
+    ///                 let hashed = hash(password);
+    ///                 set.insert("password", hashed);
+    ///             }
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    async fn updating_with_query(
+        model: &mut Model<'_, M>,
+        query: &Document,
+        update: &mut Document,
+    ) -> MongodbResult<()> {
+        Self::updating(model).await
+    }
+
     /// this call when document is created , in these observers can't call save again
     #[inline]
     async fn created(model: &mut Model<'_, M>) -> MongodbResult<()> {
@@ -26,9 +77,176 @@ pub trait Observer<M> {
         Ok(())
     }
 
+    /// this call before a document is deleted by delete , soft_delete or find_one_and_delete
+    /// an error aborts the delete
+    #[inline]
+    async fn deleting(model: &mut Model<'_, M>) -> MongodbResult<()> {
+        Ok(())
+    }
+
     /// this call when document is delete
     #[inline]
     async fn deleted(model: &mut Model<'_, M>) -> MongodbResult<()> {
         Ok(())
     }
+
+    /// this is what is called when document is created , it gets the `_id` of the document in the
+    /// context and by default it calls `created`
+    #[inline]
+    async fn created_with_context(
+        model: &mut Model<'_, M>,
+        context: &ObserverContext,
+    ) -> MongodbResult<()> {
+        Self::created(model).await
+    }
+
+    /// this is what is called when document is updated , it gets the fields that were set and for
+    /// `save` their values before the write in the context , by default it calls `updated`
+    /// ```ignore
+    /// impl Observer<User> for User {
+    ///     async fn updated_with_context(
+    ///         model: &mut Model<'_, User>,
+    ///         context: &ObserverContext,
+    ///     ) -> MongodbResult<()> {
+    ///         for (field, value) in context.changed_fields() {
+    ///             println!("{field} : {:?} -> {value}", context.previous_value(&field));
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    async fn updated_with_context(
+        model: &mut Model<'_, M>,
+        context: &ObserverContext,
+    ) -> MongodbResult<()> {
+        Self::updated(model).await
+    }
+
+    /// this is what is called when document is delete , it gets the filter of the delete in the
+    /// context and by default it calls `deleted`
+    #[inline]
+    async fn deleted_with_context(
+        model: &mut Model<'_, M>,
+        context: &ObserverContext,
+    ) -> MongodbResult<()> {
+        Self::deleted(model).await
+    }
+}
+
+/// The change that `created_with_context` , `updated_with_context` or `deleted_with_context`
+/// is called for
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObserverContext {
+    /// the `_id` of the document , it's None when the write doesn't tell it , like `patch` or
+    /// `delete` of a filter
+    pub id: Option<Bson>,
+    /// for an update the fields that were set
+    pub changes: Option<Document>,
+    /// for an update by `save` or `upsert` the values of the set fields before the write
+    pub previous: Option<Document>,
+    /// for a delete the filter of the deleted document
+    pub filter: Option<Document>,
+}
+
+impl ObserverContext {
+    pub(crate) fn created(id: Bson) -> Self {
+        ObserverContext {
+            id: Some(id),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn updated(id: Option<Bson>, changes: Document, previous: Option<Document>) -> Self {
+        ObserverContext {
+            id,
+            changes: Some(changes),
+            previous,
+            filter: None,
+        }
+    }
+
+    pub(crate) fn deleted(id: Option<Bson>, filter: Document) -> Self {
+        ObserverContext {
+            id,
+            filter: Some(filter),
+            ..Default::default()
+        }
+    }
+
+    /// the set fields whose values are different from the previous ones , all of the set fields
+    /// when the previous values aren't known
+    pub fn changed_fields(&self) -> Document {
+        let Some(changes) = &self.changes else {
+            return Document::new();
+        };
+        match &self.previous {
+            Some(previous) => changes
+                .iter()
+                .filter(|(field, value)| previous.get(field.as_str()) != Some(*value))
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect(),
+            None => changes.clone(),
+        }
+    }
+
+    /// the value of the field before the update , None when it didn't exist or isn't known
+    pub fn previous_value(&self, field: &str) -> Option<&Bson> {
+        self.previous.as_ref()?.get(field)
+    }
+}
+
+/// The kind of write that a `GlobalObserver` is notified about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// This observer is called for every model , unlike `Observer` which is implemented per model
+/// it's useful for app-wide concerns like an audit log , set it with `Spark::set_global_observer`
+/// ```ignore
+/// struct Audit;
+///
+/// impl GlobalObserver for Audit {
+///     fn on_write(&self, collection: &str, op: WriteOp, doc: &Document) {
+///         println!("{op:?} on {collection} : {doc}");
+///     }
+/// }
+///
+/// Spark::set_global_observer(Audit).unwrap();
+/// ```
+pub trait GlobalObserver: Send + Sync {
+    /// this call after `save` or `delete` wrote to database and before the model observer
+    /// for insert and update `doc` is the written document , for delete it's the filter
+    fn on_write(&self, collection: &str, op: WriteOp, doc: &Document);
+
+    /// this call after `Model::bulk_write` wrote all of its writes , `on_write` isn't called
+    /// for the writes of a bulk write
+    #[inline]
+    #[allow(unused_variables)]
+    fn on_bulk_write(&self, collection: &str, result: &BulkWriteResult) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn changed_fields_skip_the_same_values() {
+        let context = ObserverContext::updated(
+            None,
+            doc! { "title": "Published", "body": "Text" },
+            Some(doc! { "title": "Draft", "body": "Text" }),
+        );
+        assert_eq!(context.changed_fields(), doc! { "title": "Published" });
+        assert_eq!(context.previous_value("title"), Some(&Bson::from("Draft")));
+
+        // without the previous values all of the set fields are changed
+        let context = ObserverContext::updated(None, doc! { "title": "Published" }, None);
+        assert_eq!(context.changed_fields(), doc! { "title": "Published" });
+        assert_eq!(context.previous_value("title"), None);
+    }
 }
@@ -0,0 +1,17 @@
+use crate::model::MongodbResult;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{to_document, Bson};
+use serde::Serialize;
+
+/// the ids that the field of the item references , the field holds one ObjectId or an array of
+/// them , a missing or null field and the values that aren't ObjectIds reference nothing
+pub(crate) fn reference_ids<M: Serialize>(item: &M, field: &str) -> MongodbResult<Vec<ObjectId>> {
+	let ids = match to_document(item)?.remove(field) {
+		Some(Bson::ObjectId(id)) => vec![id],
+		Some(Bson::Array(values)) => {
+			values.into_iter().filter_map(|value| value.as_object_id()).collect()
+		}
+		_ => Vec::new(),
+	};
+	Ok(ids)
+}
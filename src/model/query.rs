@@ -0,0 +1,163 @@
+use crate::error::Error;
+use crate::futures::{Stream, TryStreamExt};
+use crate::model::observer::Observer;
+use crate::model::util::{AfterLoad, ModelId, ModelTimestamps, SoftDelete};
+use crate::model::validation::Validate;
+use crate::model::{Model, MongodbResult};
+use mongodb::bson::{doc, Document};
+use mongodb::options::{Collation, FindOptions};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// A find of `Model::query` , the chained calls are compiled into one filter and one
+/// `FindOptions` that are passed to `Model::find_stream`
+/// ```ignore
+/// let users = user_model
+///     .query()
+///     .filter(doc! { "age": { "$gt": 18 } })
+///     .sort_desc("age")
+///     .sort_asc("name")
+///     .project(&["name", "age"])
+///     .skip(20)
+///     .limit(10)
+///     .collect()
+///     .await?;
+/// ```
+#[derive(Debug)]
+pub struct Query<'m, 'a, M> {
+	model: &'m Model<'a, M>,
+	filter: Document,
+	sort: Document,
+	projection: Option<Document>,
+	skip: Option<u64>,
+	limit: Option<i64>,
+	collation: Option<Collation>,
+	// the error of a wrong projection , it's returned by `collect` or `stream`
+	error: Option<String>,
+}
+
+impl<'m, 'a, M> Query<'m, 'a, M>
+where
+	M: Default,
+	M: Serialize,
+	M: DeserializeOwned,
+	M: Send,
+	M: Sync,
+	M: Unpin,
+	M: Debug,
+	M: ModelTimestamps,
+	M: ModelId,
+	M: Observer<M>,
+	M: AfterLoad,
+	M: SoftDelete,
+	M: Validate,
+{
+	pub(crate) fn new(model: &'m Model<'a, M>) -> Self {
+		Query {
+			model,
+			filter: doc! {},
+			sort: doc! {},
+			projection: None,
+			skip: None,
+			limit: None,
+			collation: None,
+			error: None,
+		}
+	}
+
+	/// adds the conditions of the filter , a later condition on the same field replaces
+	/// the earlier one
+	pub fn filter(mut self, filter: Document) -> Self {
+		self.filter.extend(filter);
+		self
+	}
+
+	/// sorts ascending on the field after the sorts that are already added
+	pub fn sort_asc(mut self, field: &str) -> Self {
+		self.sort.insert(field, 1);
+		self
+	}
+
+	/// sorts descending on the field after the sorts that are already added
+	pub fn sort_desc(mut self, field: &str) -> Self {
+		self.sort.insert(field, -1);
+		self
+	}
+
+	/// loads only the fields , a field that starts with `-` is excluded instead , ex : `&["-password"]`
+	///
+	/// the included and excluded fields can't be mixed except excluding `_id` , the fields that aren't
+	/// loaded must have a default in `M` , like `#[serde(default)]`
+	pub fn project(mut self, fields: &[&str]) -> Self {
+		match projection(fields) {
+			Ok(projection) => self.projection = Some(projection),
+			Err(message) => self.error = Some(message),
+		}
+		self
+	}
+
+	pub fn skip(mut self, skip: u64) -> Self {
+		self.skip = Some(skip);
+		self
+	}
+
+	pub fn limit(mut self, limit: i64) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+
+	/// compares the strings of the filter and the sorts by the collation , like
+	/// `index::case_insensitive()` , an index is used only if it has the same collation
+	pub fn collation(mut self, collation: Collation) -> Self {
+		self.collation = Some(collation);
+		self
+	}
+
+	/// runs the find and collects the models , it fails on the first document that can't be loaded
+	pub async fn collect(self) -> MongodbResult<Vec<M>> {
+		self.stream().await?.try_collect().await
+	}
+
+	/// runs the find and streams the models like `Model::find_stream`
+	pub async fn stream(
+		self,
+	) -> MongodbResult<impl Stream<Item = MongodbResult<M>> + Send + Unpin + 'm> {
+		if let Some(message) = self.error {
+			return Err(Error::new(&message).into());
+		}
+		let options = FindOptions::builder()
+			.sort((!self.sort.is_empty()).then_some(self.sort))
+			.projection(self.projection)
+			.skip(self.skip)
+			.limit(self.limit)
+			.collation(self.collation)
+			.build();
+		self.model.find_stream(self.filter, options).await
+	}
+}
+
+/// the projection of the fields , a field that starts with `-` is excluded , the included and
+/// excluded fields can't be mixed except excluding `_id`
+pub(crate) fn projection(fields: &[&str]) -> Result<Document, String> {
+	let mut projection = Document::new();
+	let (mut included, mut excluded) = (false, false);
+	for field in fields {
+		match field.strip_prefix('-') {
+			Some(field) => {
+				excluded |= field != "_id";
+				projection.insert(field, 0);
+			}
+			None => {
+				included = true;
+				projection.insert(*field, 1);
+			}
+		}
+	}
+	if included && excluded {
+		return Err(format!(
+			"The projection {fields:?} mixes included and excluded fields , only _id can be excluded from an inclusion"
+		));
+	}
+	Ok(projection)
+}
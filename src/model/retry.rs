@@ -0,0 +1,210 @@
+use mongodb::error::{
+	Error, ErrorKind, WriteFailure, RETRYABLE_WRITE_ERROR, TRANSIENT_TRANSACTION_ERROR,
+};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::macros::trace;
+
+/// the server error code of a WiredTiger write conflict
+const WRITE_CONFLICT_CODE: i32 = 112;
+
+/// the server error codes of a read that can succeed on another try , like a primary stepdown ,
+/// they are the codes that the driver retries once by itself
+const RETRYABLE_READ_CODES: [i32; 13] =
+	[11600, 11602, 10107, 13435, 13436, 189, 91, 7, 6, 89, 9001, 134, 262];
+
+/// The retries of `Model::with_retry` , a failed operation runs again after a delay that doubles
+/// on every retry
+/// ```ignore
+/// let policy = RetryPolicy::new(5).base_delay(Duration::from_millis(50)).jitter(true);
+/// let user_model = User::new_model(None).with_retry(policy);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// how many times the operation runs , including the first try
+	pub max_attempts: u32,
+	/// the delay before the first retry
+	pub base_delay: Duration,
+	/// the longest delay between two tries
+	pub max_delay: Duration,
+	/// waits a random part of each delay , so the clients that failed together don't retry together
+	pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		RetryPolicy {
+			max_attempts: 3,
+			base_delay: Duration::from_millis(100),
+			max_delay: Duration::from_secs(5),
+			jitter: true,
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn new(max_attempts: u32) -> Self {
+		RetryPolicy {
+			max_attempts,
+			..Default::default()
+		}
+	}
+
+	pub fn base_delay(mut self, base_delay: Duration) -> Self {
+		self.base_delay = base_delay;
+		self
+	}
+
+	pub fn max_delay(mut self, max_delay: Duration) -> Self {
+		self.max_delay = max_delay;
+		self
+	}
+
+	pub fn jitter(mut self, jitter: bool) -> Self {
+		self.jitter = jitter;
+		self
+	}
+
+	/// the delay after the failed `attempt` , it starts from 1 , with jitter it's between the half
+	/// and the whole of the exponential delay
+	pub fn delay(&self, attempt: u32) -> Duration {
+		let exponential = self
+			.base_delay
+			.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+			.min(self.max_delay);
+		if !self.jitter {
+			return exponential;
+		}
+		// every RandomState has random keys , so the hash of nothing is a random number
+		let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+		exponential.mul_f64(0.5 + random / 2.0)
+	}
+}
+
+/// the tries of one operation under the policy of `Model::with_retry` , without a policy the
+/// operation runs once
+pub(crate) struct Attempts<'a> {
+	policy: Option<RetryPolicy>,
+	collection_name: &'a str,
+	operation: &'static str,
+	attempt: u32,
+}
+
+impl<'a> Attempts<'a> {
+	pub(crate) fn new(
+		policy: Option<RetryPolicy>,
+		collection_name: &'a str,
+		operation: &'static str,
+	) -> Self {
+		Attempts {
+			policy,
+			collection_name,
+			operation,
+			attempt: 1,
+		}
+	}
+
+	/// waits before the next try of the failed operation , it's false when the error must be
+	/// returned because it isn't `retryable` or the policy doesn't allow another try
+	pub(crate) async fn retry(&mut self, error: &Error, retryable: fn(&Error) -> bool) -> bool {
+		let Some(policy) = self.policy else {
+			return false;
+		};
+		if self.attempt >= policy.max_attempts || !retryable(error) {
+			return false;
+		}
+		trace!("Retry {} on {} after {error}", self.operation, self.collection_name);
+		tokio::time::sleep(policy.delay(self.attempt)).await;
+		self.attempt += 1;
+		true
+	}
+}
+
+/// how many times `save_retrying` tries the write , including the first try
+pub(crate) const SAVE_ATTEMPTS: u32 = 3;
+
+/// the wait before the first retry of `save_retrying` , it doubles on every retry
+pub(crate) const SAVE_BACKOFF: Duration = Duration::from_millis(20);
+
+/// returns the server error code of a command or write error
+pub(crate) fn error_code(error: &Error) -> Option<i32> {
+	match &*error.kind {
+		ErrorKind::Command(command_error) => Some(command_error.code),
+		ErrorKind::Write(WriteFailure::WriteError(write_error)) => Some(write_error.code),
+		ErrorKind::BulkWrite(failure) => failure
+			.write_errors
+			.as_ref()
+			.and_then(|errors| errors.first())
+			.map(|write_error| write_error.code),
+		_ => None,
+	}
+}
+
+/// checks that the write failed because of a conflict with another write , running it again
+/// can succeed , a duplicate key error is never transient
+pub(crate) fn is_transient_write(error: &Error) -> bool {
+	error.contains_label(TRANSIENT_TRANSACTION_ERROR)
+		|| error_code(error) == Some(WRITE_CONFLICT_CODE)
+}
+
+/// checks that the write can run again , the driver labels the write errors that it would retry ,
+/// a duplicate key or a validation error is never retryable
+pub(crate) fn is_retryable_write(error: &Error) -> bool {
+	error.contains_label(RETRYABLE_WRITE_ERROR)
+}
+
+/// checks that the read can run again , a network error or a code of a lost primary
+pub(crate) fn is_retryable_read(error: &Error) -> bool {
+	matches!(&*error.kind, ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. })
+		|| error_code(error).is_some_and(|code| RETRYABLE_READ_CODES.contains(&code))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn delay_doubles_up_to_max_delay() {
+		let policy = RetryPolicy::new(5)
+			.base_delay(Duration::from_millis(100))
+			.max_delay(Duration::from_millis(300))
+			.jitter(false);
+		assert_eq!(policy.delay(1), Duration::from_millis(100));
+		assert_eq!(policy.delay(2), Duration::from_millis(200));
+		assert_eq!(policy.delay(3), Duration::from_millis(300));
+		assert_eq!(policy.delay(40), Duration::from_millis(300));
+	}
+
+	#[test]
+	fn jitter_keeps_at_least_half_of_the_delay() {
+		let policy = RetryPolicy::new(3).base_delay(Duration::from_millis(100)).jitter(true);
+		for _ in 0..100 {
+			let delay = policy.delay(2);
+			assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+		}
+	}
+
+	#[tokio::test]
+	async fn attempts_stop_at_max_attempts() {
+		let error: Error = crate::error::Error::new("The primary stepped down").into();
+		let policy = RetryPolicy::new(3).base_delay(Duration::ZERO).jitter(false);
+		let mut attempts = Attempts::new(Some(policy), "users", "find");
+		assert!(attempts.retry(&error, |_| true).await);
+		assert!(attempts.retry(&error, |_| true).await);
+		assert!(!attempts.retry(&error, |_| true).await);
+
+		let mut attempts = Attempts::new(Some(policy), "users", "find");
+		assert!(!attempts.retry(&error, |_| false).await);
+		let mut attempts = Attempts::new(None, "users", "find");
+		assert!(!attempts.retry(&error, |_| true).await);
+	}
+
+	#[test]
+	fn custom_errors_are_not_retryable() {
+		let error: Error = crate::error::Error::new("The email is invalid").into();
+		assert!(!is_retryable_write(&error));
+		assert!(!is_retryable_read(&error));
+	}
+}
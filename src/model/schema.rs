@@ -0,0 +1,91 @@
+use mongodb::bson::{doc, from_document, to_document, Bson, Document};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A sampled document that can't be deserialized into the model
+#[derive(Debug, Clone)]
+pub struct SchemaFailure {
+	/// the `_id` of the document
+	pub id: Option<Bson>,
+	/// the fields that fail on their own , like a field whose type changed
+	/// or a required field that is missing
+	pub fields: Vec<String>,
+	/// the deserialization error
+	pub message: String,
+}
+
+/// The result of `Model::schema_audit`
+#[derive(Debug, Clone, Default)]
+pub struct SchemaReport {
+	/// the count of sampled documents
+	pub sampled: usize,
+	/// the documents that can't be deserialized into the model
+	pub failures: Vec<SchemaFailure>,
+	/// the stored fields that the model doesn't have , with the count of documents that have them
+	pub unknown_fields: BTreeMap<String, usize>,
+	/// the model fields that aren't stored , with the count of documents that miss them ,
+	/// these documents only load because the field has a default
+	pub missing_fields: BTreeMap<String, usize>,
+}
+
+impl SchemaReport {
+	/// true when every sampled document matches the model
+	pub fn is_clean(&self) -> bool {
+		self.failures.is_empty() && self.unknown_fields.is_empty() && self.missing_fields.is_empty()
+	}
+
+	/// checks one stored document against the model
+	pub(crate) fn audit<M>(&mut self, stored: Document)
+	where
+		M: DeserializeOwned + Serialize,
+	{
+		self.sampled += 1;
+		let error = match from_document::<M>(stored.clone()) {
+			Ok(model) => {
+				self.compare_fields(&stored, &model);
+				return;
+			}
+			Err(error) => error.to_string(),
+		};
+
+		let mut fields = missing_field(&error).into_iter().collect::<Vec<String>>();
+		stored.iter().for_each(|(key, value)| {
+			// the fields of a model that has defaults can be checked one by one ,
+			// a missing field error here just means the model has required fields
+			if let Err(error) = from_document::<M>(doc! { key: value.clone() }) {
+				if missing_field(&error.to_string()).is_none() && !fields.contains(key) {
+					fields.push(key.clone());
+				}
+			}
+		});
+
+		self.failures.push(SchemaFailure {
+			id: stored.get("_id").cloned(),
+			fields,
+			message: error,
+		});
+	}
+
+	/// compares the stored fields with the fields of the model after a round trip
+	fn compare_fields<M: Serialize>(&mut self, stored: &Document, model: &M) {
+		let Ok(known) = to_document(model) else {
+			return;
+		};
+		stored
+			.iter()
+			// a null is what a skipped None looks like after a round trip
+			.filter(|(key, value)| !known.contains_key(key.as_str()) && **value != Bson::Null)
+			.for_each(|(key, _)| *self.unknown_fields.entry(key.clone()).or_default() += 1);
+		known
+			.keys()
+			.filter(|key| !stored.contains_key(key.as_str()))
+			.for_each(|key| *self.missing_fields.entry(key.clone()).or_default() += 1);
+	}
+}
+
+/// reads the field of a serde missing field error
+fn missing_field(message: &str) -> Option<String> {
+	let field = message.split("missing field `").nth(1)?;
+	field.split('`').next().map(str::to_string)
+}
@@ -0,0 +1,119 @@
+use crate::futures::{stream, Stream, StreamExt};
+use crate::model::retry::error_code;
+use crate::model::util::AfterLoad;
+use crate::model::MongodbResult;
+use mongodb::bson::{doc, from_document, Bson, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Cursor};
+use serde::de::DeserializeOwned;
+
+/// the server error code of a cursor that was killed , for example by the cursor timeout
+const CURSOR_NOT_FOUND_CODE: i32 = 43;
+
+struct Resilient {
+	collection: Collection<Document>,
+	filter: Document,
+	sort_field: String,
+	cursor: Option<Cursor<Document>>,
+	last: Option<Bson>,
+	// true when the cursor was reopened and no document is read since then
+	resumed: bool,
+	done: bool,
+}
+
+impl Resilient {
+	/// opens the cursor after the last seen value of the sort field
+	async fn open(&mut self) -> MongodbResult<Cursor<Document>> {
+		let filter = resume_filter(&self.filter, &self.sort_field, self.last.as_ref());
+		let options = FindOptions::builder().sort(doc! { &self.sort_field: 1 }).build();
+		self.collection.find(filter, options).await
+	}
+
+	/// reads the next document , it reopens the cursor once if the server killed it
+	async fn next_document(&mut self) -> Option<MongodbResult<Document>> {
+		loop {
+			let cursor = match &mut self.cursor {
+				Some(cursor) => cursor,
+				None => match self.open().await {
+					Ok(cursor) => self.cursor.insert(cursor),
+					Err(error) => return Some(Err(error)),
+				},
+			};
+			match cursor.next().await {
+				Some(Ok(document)) => {
+					self.resumed = false;
+					self.last = document.get(&self.sort_field).cloned();
+					return Some(Ok(document));
+				}
+				Some(Err(error))
+					if !self.resumed && error_code(&error) == Some(CURSOR_NOT_FOUND_CODE) =>
+				{
+					self.resumed = true;
+					self.cursor = None;
+				}
+				other => return other,
+			}
+		}
+	}
+}
+
+/// the filter of the caller and , after a resume , the documents after the last seen value , the
+/// filter is kept whole so its own `$and` or conditions on the sort field still apply
+fn resume_filter(filter: &Document, sort_field: &str, last: Option<&Bson>) -> Document {
+	match last {
+		Some(last) => doc! {
+			"$and": [filter.clone(), { sort_field: { "$gt": last.clone() } }]
+		},
+		None => filter.clone(),
+	}
+}
+
+/// streams the documents of the filter ordered by `sort_field` , when the server kills the cursor
+/// the query is issued again for the documents after the last seen value of `sort_field`
+pub(crate) fn resilient_stream<M>(
+	collection: Collection<Document>,
+	filter: Document,
+	sort_field: &str,
+) -> impl Stream<Item = MongodbResult<M>> + Send + Unpin + 'static
+where
+	M: DeserializeOwned + AfterLoad + Send + 'static,
+{
+	let state = Resilient {
+		collection,
+		filter,
+		sort_field: sort_field.to_string(),
+		cursor: None,
+		last: None,
+		resumed: false,
+		done: false,
+	};
+	Box::pin(stream::unfold(state, |mut state| async move {
+		if state.done {
+			return None;
+		}
+		let item = match state.next_document().await? {
+			Ok(document) => from_document::<M>(document).map_err(Into::into).map(|mut inner| {
+				inner.after_load();
+				inner
+			}),
+			Err(error) => Err(error),
+		};
+		state.done = item.is_err();
+		Some((item, state))
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resume_keeps_the_filter_of_the_caller() {
+		let filter = doc! { "$and": [{ "age": { "$gte": 18 } }, { "age": { "$lt": 65 } }] };
+		assert_eq!(resume_filter(&filter, "_id", None), filter);
+		assert_eq!(
+			resume_filter(&filter, "_id", Some(&Bson::Int32(7))),
+			doc! { "$and": [filter.clone(), { "_id": { "$gt": 7 } }] }
+		);
+	}
+}
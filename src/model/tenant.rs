@@ -0,0 +1,14 @@
+/// This trait marks a model that lives in a multi-tenant collection
+/// the field returned by `tenant_field` is injected into every filter of a scoped model
+/// ```ignore
+/// impl TenantScoped for User {
+///     fn tenant_field() -> &'static str {
+///         "tenant_id"
+///     }
+/// }
+///
+/// let user_model = User::new_model(None).scoped(Bson::from("tenant_42"));
+/// ```
+pub trait TenantScoped {
+	fn tenant_field() -> &'static str;
+}
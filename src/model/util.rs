@@ -1,5 +1,73 @@
+use mongodb::bson::{Bson, DateTime};
+
+/// the default field that `ModelTimestamps::created_at` fills
+pub const CREATED_AT_FIELD: &str = "created_at";
+
+/// the default field that `ModelTimestamps::updated_at` fills
+pub const UPDATED_AT_FIELD: &str = "updated_at";
+
+/// This trait is implemented by Model macro , it gives the `_id` of the model
+/// without serializing the whole model
+pub trait ModelId {
+    /// returns None if the model doesn't have an `_id` yet
+    fn model_id(&self) -> Option<Bson>;
+}
+
+/// This trait is implemented by Model macro , it sets the timestamps of the model to `now`
+/// the fields are named `created_at` and `updated_at` unless they are renamed in Model macro
+/// ```ignore
+/// #[Model(coll_name = "users", created_field = "createdAt", updated_field = "updatedAt")]
+/// #[derive(Serialize, Deserialize, Debug, Default)]
+/// struct User {
+///    name: String,
+/// }
+/// ```
+#[allow(unused)]
 pub trait ModelTimestamps {
-    fn created_at(&mut self) {}
-    fn updated_at(&mut self) {}
-    fn deleted_at(&mut self) {}
-}
\ No newline at end of file
+    /// the stored name of the field that `created_at` fills
+    const CREATED_FIELD: &'static str = CREATED_AT_FIELD;
+    /// the stored name of the field that `updated_at` fills
+    const UPDATED_FIELD: &'static str = UPDATED_AT_FIELD;
+
+    fn created_at(&mut self, now: DateTime) {}
+    fn updated_at(&mut self, now: DateTime) {}
+    fn deleted_at(&mut self, now: DateTime) {}
+}
+/// This trait implement by default for Model , a model that tells `soft_delete` to Model macro
+/// gives the field that `Model::soft_delete` sets , then the reads skip the documents that have it
+/// ```ignore
+/// #[Model(coll_name = "users", soft_delete)]
+/// #[derive(Serialize, Deserialize, Debug, Default)]
+/// struct User {
+///    name: String,
+/// }
+/// ```
+pub trait SoftDelete {
+    /// returns None if the model is deleted for real
+    fn deleted_at_field() -> Option<&'static str> {
+        None
+    }
+}
+
+/// This trait implement by default for Model , it's called after a document is loaded
+/// from database so the model can compute the fields that aren't stored
+/// but if user wants to override and use it must tell to Model macro
+/// ```ignore
+/// #[Model(coll_name = "users", after_load)]
+/// #[derive(Serialize, Deserialize, Debug, Default)]
+/// struct User {
+///    first: String,
+///    last: String,
+///    #[serde(skip)]
+///    full_name: String,
+/// }
+///
+/// impl AfterLoad for User {
+///     fn after_load(&mut self) {
+///         self.full_name = format!("{} {}", self.first, self.last);
+///     }
+/// }
+/// ```
+pub trait AfterLoad {
+    fn after_load(&mut self) {}
+}
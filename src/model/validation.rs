@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// This trait implement by default for Model , it's called by `save` and `save_many` before
+/// anything is written , an error aborts the write and the timestamps aren't changed
+/// but if user wants to override and use it must tell to Model macro
+/// ```ignore
+/// #[Model(coll_name = "users", validate)]
+/// #[derive(Serialize, Deserialize, Debug, Default)]
+/// struct User {
+///    email: String,
+///    age: u64,
+/// }
+///
+/// impl Validate for User {
+///     fn validate(&self) -> Result<(), ValidationErrors> {
+///         let mut errors = ValidationErrors::new();
+///         if !self.email.contains('@') {
+///             errors.add("email", "The email is invalid");
+///         }
+///         if self.age < 18 {
+///             errors.add("age", "The user must be an adult");
+///         }
+///         errors.into_result()
+///     }
+/// }
+/// ```
+pub trait Validate {
+	fn validate(&self) -> Result<(), ValidationErrors> {
+		Ok(())
+	}
+}
+
+/// When `save` calls `Validate::validate` , it's set by `Model::with_validation_order`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValidationOrder {
+	/// first , before `saving` and `creating` or `updating` , an invalid model runs none of them
+	#[default]
+	BeforeHooks,
+	/// after `creating` or `updating` , so the values that the hooks set , like a slug or a hashed
+	/// password , are validated too
+	AfterHooks,
+}
+
+/// The messages of the invalid fields , it's returned inside the error of `save` and `save_many` ,
+/// the supported ways to get it are `SparkError::from(error)` that is `SparkError::Validation` or
+/// `error.get_custom::<ValidationErrors>()` on the error itself
+/// ```ignore
+/// match user_model.save(None).await.map_err(SparkError::from) {
+///     Err(SparkError::Validation(errors)) => respond(422, errors.fields()),
+///     other => {
+///         other?;
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+	fields: BTreeMap<String, Vec<String>>,
+}
+
+impl ValidationErrors {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// adds a message for the field , a field can have several messages
+	pub fn add(&mut self, field: &str, message: &str) {
+		self.fields.entry(field.to_string()).or_default().push(message.to_string());
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.fields.is_empty()
+	}
+
+	/// the messages of the field , it's empty when the field is valid
+	pub fn get(&self, field: &str) -> &[String] {
+		self.fields.get(field).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	/// the invalid fields with their messages , ordered by the field
+	pub fn fields(&self) -> &BTreeMap<String, Vec<String>> {
+		&self.fields
+	}
+
+	/// returns Ok when no message is added
+	pub fn into_result(self) -> Result<(), Self> {
+		if self.is_empty() {
+			Ok(())
+		} else {
+			Err(self)
+		}
+	}
+}
+
+impl Display for ValidationErrors {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "The model is invalid")?;
+		for (field, messages) in &self.fields {
+			write!(f, " , {field} : {}", messages.join(" , "))?;
+		}
+		Ok(())
+	}
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl From<ValidationErrors> for mongodb::error::Error {
+	fn from(value: ValidationErrors) -> Self {
+		mongodb::error::Error::custom(value)
+	}
+}
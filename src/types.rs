@@ -15,3 +15,172 @@
 //         write!(f, "{:?}", self.0)
 //     }
 // }
+
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken};
+use serde::Serialize;
+
+/// The insertion order of documents , it's useful for capped collections used as a log
+/// it converts into the `{ $natural: 1 }` or `{ $natural: -1 }` sort document
+/// so it can be passed as the sort of `find_one_sorted` , `FindOptions` or `FindOneOptions`
+///
+/// the natural order scans the collection without an index , and it can't be mixed with
+/// other sort fields
+/// ```ignore
+/// // the oldest entry of the log
+/// log_model.find_one_sorted(doc! {}, NaturalOrder::Forward, None).await?;
+///
+/// // the newest entries of the log
+/// let options = FindOptions::builder().sort(NaturalOrder::Reverse.sort()).build();
+/// let entries = log_model.find_and_collect(doc! {}, options).await?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NaturalOrder {
+    /// the insertion order , oldest first
+    Forward,
+    /// the reverse insertion order , newest first
+    Reverse,
+}
+
+impl NaturalOrder {
+    pub fn sort(self) -> Document {
+        let direction = match self {
+            NaturalOrder::Forward => 1,
+            NaturalOrder::Reverse => -1,
+        };
+        doc! { "$natural": direction }
+    }
+}
+
+impl From<NaturalOrder> for Document {
+    fn from(value: NaturalOrder) -> Self {
+        value.sort()
+    }
+}
+
+/// One page of `Model::paginate` with the metadata of the whole result
+/// ```ignore
+/// let page = user_model.paginate(doc! { "age": { "$gt": 18 } }, 2, 20).await?;
+/// println!("page {} of {} , {} users in total", page.page, page.total_pages, page.total);
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<M> {
+    /// the models of this page
+    pub items: Vec<M>,
+    /// the count of all documents of the filter
+    pub total: u64,
+    /// the number of this page , it starts from 1
+    pub page: u64,
+    pub per_page: u64,
+    /// the count of pages needed for `total` documents
+    pub total_pages: u64,
+}
+
+impl<M> Page<M> {
+    pub(crate) fn new(items: Vec<M>, total: u64, page: u64, per_page: u64) -> Self {
+        Page {
+            items,
+            total,
+            page,
+            per_page,
+            total_pages: total.div_ceil(per_page),
+        }
+    }
+}
+
+/// One change of `Model::watch`
+/// ```ignore
+/// let options = ChangeStreamOptions::builder().resume_after(saved_token).build();
+/// let mut changes = user_model.watch(vec![], options).await?;
+/// while let Some(change) = changes.next().await {
+///     let change = change?;
+///     if change.operation == OperationType::Insert {
+///         println!("new user {:?}", change.full_document);
+///     }
+///     save_token(&change.resume_token);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChangeEvent<M> {
+    /// persist it and pass it to `ChangeStreamOptions::resume_after` to continue after this change ,
+    /// for example after a restart
+    pub resume_token: ResumeToken,
+    /// insert , update , replace , delete or an event of the collection itself like drop
+    pub operation: OperationType,
+    /// the document after the change , it's None for a delete and for an update unless the stream
+    /// is opened with `FullDocumentType::UpdateLookup`
+    pub full_document: Option<M>,
+    /// the `_id` of the changed document , and the shard key for a sharded collection
+    pub document_key: Option<Document>,
+}
+
+impl<M> From<ChangeStreamEvent<M>> for ChangeEvent<M> {
+    fn from(event: ChangeStreamEvent<M>) -> Self {
+        ChangeEvent {
+            resume_token: event.id,
+            operation: event.operation_type,
+            full_document: event.full_document,
+            document_key: event.document_key,
+        }
+    }
+}
+
+/// What `Model::save` did , it carries the `_id` of the document
+/// ```ignore
+/// match user_model.save(None).await? {
+///     SaveOutcome::Created(id) => println!("new user {id}"),
+///     SaveOutcome::Updated(id) => println!("updated user {id}"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveOutcome {
+    /// the model had no _id , so a new document is inserted
+    Created(Bson),
+    /// the document of the _id is updated , even when none of its values changed
+    Updated(Bson),
+}
+
+impl SaveOutcome {
+    pub fn id(&self) -> &Bson {
+        match self {
+            SaveOutcome::Created(id) | SaveOutcome::Updated(id) => id,
+        }
+    }
+
+    pub fn into_id(self) -> Bson {
+        match self {
+            SaveOutcome::Created(id) | SaveOutcome::Updated(id) => id,
+        }
+    }
+
+    pub fn is_created(&self) -> bool {
+        matches!(self, SaveOutcome::Created(_))
+    }
+}
+
+/// What `Model::upsert` did , it carries the `_id` of the document
+/// ```ignore
+/// match user_model.upsert(doc! { "email": "john@mail.com" }, None).await? {
+///     UpsertOutcome::Inserted(id) => println!("new user {id}"),
+///     UpsertOutcome::Updated(id) => println!("updated user {id}"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpsertOutcome {
+    /// no document matched the query , so a new one is inserted
+    Inserted(Bson),
+    /// the first document of the query is updated
+    Updated(Bson),
+}
+
+impl UpsertOutcome {
+    pub fn id(&self) -> &Bson {
+        match self {
+            UpsertOutcome::Inserted(id) | UpsertOutcome::Updated(id) => id,
+        }
+    }
+
+    pub fn is_inserted(&self) -> bool {
+        matches!(self, UpsertOutcome::Inserted(_))
+    }
+}
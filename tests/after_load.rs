@@ -0,0 +1,37 @@
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use spark_orm::model::util::AfterLoad;
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+
+#[Model(coll_name = "after_load_users", after_load)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct User {
+	first: String,
+	last: String,
+	#[serde(skip)]
+	full_name: String,
+}
+
+impl AfterLoad for User {
+	fn after_load(&mut self) {
+		self.full_name = format!("{} {}", self.first, self.last);
+	}
+}
+
+#[tokio::test]
+async fn find_one_computes_virtual_field() {
+	connect_db().await;
+	let mut user_model = User::new_model(None);
+	user_model.first = "Hossein".to_string();
+	user_model.last = "Ahmadi".to_string();
+	user_model.save(None).await.unwrap();
+
+	let mut loaded = User::new_model(None);
+	let found = loaded.find_one(doc! { "first": "Hossein" }, None).await.unwrap().unwrap();
+	assert_eq!(found.full_name, "Hossein Ahmadi");
+}
+
+async fn connect_db() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+}
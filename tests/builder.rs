@@ -0,0 +1,21 @@
+use spark_orm::SparkBuilder;
+use std::time::Duration;
+
+#[tokio::test]
+async fn build_fails_on_invalid_uri() {
+	let result = SparkBuilder::new("localhost:27017", "rm_orm_db").build().await;
+	assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn build_fails_when_the_ping_fails() {
+	// nothing listens on the port , so the ping of build times out
+	let result = SparkBuilder::new("mongodb://localhost:1", "rm_orm_db")
+		.server_selection_timeout(Duration::from_millis(100))
+		.connect_timeout(Duration::from_millis(100))
+		.max_pool_size(2)
+		.app_name("builder-test")
+		.build()
+		.await;
+	assert!(result.is_err());
+}
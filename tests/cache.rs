@@ -0,0 +1,42 @@
+#![cfg(feature = "cache")]
+
+use mongodb::bson::{doc, Document};
+use serde::{Deserialize, Serialize};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::time::Duration;
+
+#[Model(coll_name = "cache_countries")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Country {
+	code: String,
+}
+
+#[tokio::test]
+async fn cached_reads_are_forgotten_after_a_write() {
+	connect_db().await;
+	let db = Spark::get_db();
+	let raw = db.collection::<Document>("cache_countries");
+	raw.delete_many(doc! {}, None).await.unwrap();
+	raw.insert_one(doc! { "code": "IR" }, None).await.unwrap();
+
+	let mut countries = Country::new_model(None).with_cache(Duration::from_secs(60));
+	let first = countries.find_and_collect(doc! {}, None).await.unwrap();
+	assert_eq!(first.len(), 1);
+
+	// a write that doesn't go through the model isn't seen until the ttl passes
+	raw.insert_one(doc! { "code": "DE" }, None).await.unwrap();
+	let cached = countries.find_and_collect(doc! {}, None).await.unwrap();
+	assert_eq!(cached.len(), 1);
+	// a read with another filter isn't remembered yet
+	assert!(countries.fetch(doc! { "code": "DE" }, None).await.unwrap().is_some());
+
+	countries.code = "FR".to_string();
+	countries.save(None).await.unwrap();
+	let fresh = countries.find_and_collect(doc! {}, None).await.unwrap();
+	assert_eq!(fresh.len(), 3);
+}
+
+async fn connect_db() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+}
@@ -0,0 +1,69 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{Bson, DateTime};
+use serde::{Deserialize, Serialize};
+use spark_orm::model::util::{ModelId, ModelTimestamps, SoftDelete};
+use spark_orm::{RsparkModel, Spark};
+use std::sync::Arc;
+
+#[derive(RsparkModel, Serialize, Deserialize, Default, Debug)]
+#[rspark(coll_name = "derived_users", created_field = "inserted_at", soft_delete)]
+struct User {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	_id: Option<ObjectId>,
+	#[serde(rename = "insertedAt")]
+	inserted_at: Option<DateTime>,
+	updated_at: Option<DateTime>,
+	deleted_at: Option<DateTime>,
+	#[index]
+	name: String,
+}
+
+#[derive(RsparkModel, Serialize, Deserialize, Default, Debug)]
+#[rspark(coll_name = "derived_posts", deleted_field = "removed_at", soft_delete)]
+struct Post {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	_id: Option<ObjectId>,
+	created_at: Option<DateTime>,
+	updated_at: Option<DateTime>,
+	#[serde(rename = "removedAt")]
+	removed_at: Option<DateTime>,
+	title: String,
+}
+
+#[test]
+fn derive_wires_the_declared_fields() {
+	let mut user = User::default();
+	assert_eq!(user.model_id(), None);
+
+	let id = ObjectId::new();
+	user._id = Some(id);
+	let now = DateTime::now();
+	user.created_at(now);
+	user.updated_at(now);
+
+	assert_eq!(user.model_id(), Some(Bson::ObjectId(id)));
+	assert_eq!(user.inserted_at, Some(now));
+	assert_eq!(user.updated_at, Some(now));
+	// the stored name of a renamed field is the serde name
+	assert_eq!(User::CREATED_FIELD, "insertedAt");
+	assert_eq!(User::UPDATED_FIELD, "updated_at");
+	assert_eq!(User::deleted_at_field(), Some("deleted_at"));
+}
+
+#[test]
+fn derive_wires_the_declared_deleted_field() {
+	let mut post = Post::default();
+	let now = DateTime::now();
+	post.deleted_at(now);
+
+	assert_eq!(post.removed_at, Some(now));
+	assert_eq!(Post::deleted_at_field(), Some("removedAt"));
+}
+
+#[tokio::test]
+async fn derive_generates_new_model() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Derived".to_string();
+	assert_eq!(user_model.name, "Derived");
+}
@@ -0,0 +1,49 @@
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Point {
+	#[serde(rename = "type")]
+	kind: String,
+	coordinates: Vec<f64>,
+}
+
+#[Model(coll_name = "places")]
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Place {
+	name: String,
+	location: Point,
+}
+
+#[tokio::test]
+async fn find_near_rejects_invalid_coordinates() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let place_model = Place::new_model(Some(&db));
+	assert!(place_model.find_near("location", 181.0, 0.0, 10.0).await.is_err());
+	assert!(place_model.find_near("location", 0.0, -90.5, 10.0).await.is_err());
+	assert!(place_model.find_near("location", f64::NAN, 0.0, 10.0).await.is_err());
+	assert!(place_model.find_near("location", 0.0, 0.0, -1.0).await.is_err());
+}
+
+#[tokio::test]
+async fn find_near() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut place_model = Place::new_model(Some(&db));
+	place_model.register_geo_index("location").await.unwrap();
+	place_model.delete_many(doc! {}, None).await.unwrap();
+
+	place_model.name = "Eiffel Tower".to_string();
+	place_model.location = Point {
+		kind: "Point".to_string(),
+		coordinates: vec![2.2945, 48.8584],
+	};
+	place_model.save(None).await.unwrap();
+
+	let near = place_model.find_near("location", 2.2950, 48.8580, 500.0).await.unwrap();
+	assert_eq!(near.len(), 1);
+	let far = place_model.find_near("location", 2.3522, 48.8566, 500.0).await.unwrap();
+	assert!(far.is_empty());
+}
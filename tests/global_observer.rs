@@ -0,0 +1,73 @@
+use mongodb::bson::{doc, Document};
+use serde::{Deserialize, Serialize};
+use spark_orm::futures::FutureExt;
+use spark_orm::model::observer::{GlobalObserver, WriteOp};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::Mutex;
+
+static WRITES: Mutex<Vec<(String, WriteOp)>> = Mutex::new(Vec::new());
+
+struct Audit;
+
+impl GlobalObserver for Audit {
+	fn on_write(&self, collection: &str, op: WriteOp, _doc: &Document) {
+		WRITES.lock().unwrap().push((collection.to_string(), op));
+	}
+}
+
+#[Model(coll_name = "audited_users")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct User {
+	name: String,
+}
+
+#[tokio::test]
+async fn save_and_delete_notify_global_observer() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+	Spark::set_global_observer(Audit).unwrap();
+
+	let mut user_model = User::new_model(None);
+	user_model.name = "Audited".to_string();
+	user_model.save(None).await.unwrap();
+	user_model.delete(doc! { "name": "Audited" }, None).await.unwrap();
+
+	assert_eq!(
+		*WRITES.lock().unwrap(),
+		vec![
+			("audited_users".to_string(), WriteOp::Insert),
+			("audited_users".to_string(), WriteOp::Delete)
+		]
+	);
+	WRITES.lock().unwrap().clear();
+
+	// the writes of an aborted transaction are never notified
+	let mut user_model = User::new_model(None);
+	user_model.name = "Aborted".to_string();
+	let aborted = Spark::transaction(move |session| {
+		async move {
+			user_model.save_with_session(session, None).await?;
+			Err::<(), _>(spark_orm::error::Error::new("The order is rejected").into())
+		}
+		.boxed()
+	})
+	.await;
+	assert!(aborted.is_err());
+	assert!(WRITES.lock().unwrap().is_empty());
+
+	// the writes of a committed transaction are notified by dispatch_committed
+	let mut user_model = User::new_model(None);
+	user_model.name = "Committed".to_string();
+	let mut user_model = Spark::transaction(move |session| {
+		async move {
+			user_model.save_with_session(session, None).await?;
+			Ok(user_model)
+		}
+		.boxed()
+	})
+	.await
+	.unwrap();
+	assert!(WRITES.lock().unwrap().is_empty());
+	user_model.dispatch_committed().await.unwrap();
+	assert_eq!(*WRITES.lock().unwrap(), vec![("audited_users".to_string(), WriteOp::Insert)]);
+}
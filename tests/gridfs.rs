@@ -0,0 +1,28 @@
+use spark_orm::futures::io::{AsyncReadExt, Cursor};
+use spark_orm::gridfs::Bucket;
+use spark_orm::Spark;
+
+#[tokio::test]
+async fn upload_and_download_stream() {
+	let db = Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+	let bucket = Bucket::new(&db, Some("avatars"));
+	let content = vec![7u8; 300 * 1024];
+
+	let id = bucket.upload_stream("avatar.png", Cursor::new(content.clone()), None).await.unwrap();
+
+	let mut downloaded = Vec::new();
+	bucket.download_stream(id).await.unwrap().read_to_end(&mut downloaded).await.unwrap();
+	assert_eq!(downloaded, content);
+
+	let mut by_name = Vec::new();
+	bucket
+		.download_stream_by_name("avatar.png")
+		.await
+		.unwrap()
+		.read_to_end(&mut by_name)
+		.await
+		.unwrap();
+	assert_eq!(by_name, content);
+
+	bucket.delete(id.into()).await.unwrap();
+}
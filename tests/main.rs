@@ -1,7 +1,14 @@
-use mongodb::bson::doc;
+use mongodb::bson::{doc, Bson, Document};
 use mongodb::bson::oid::ObjectId;
+use mongodb::change_stream::event::OperationType;
 use mongodb::Database;
 use serde::{Deserialize, Serialize};
+use spark_orm::futures::{FutureExt, StreamExt};
+use spark_orm::model::batch::InsertBatching;
+use spark_orm::model::bulk::{BulkWriteOptions, WriteOp};
+use spark_orm::model::index::{case_insensitive, IndexSpec, IndexType};
+use spark_orm::error::SparkError;
+use spark_orm::types::NaturalOrder;
 use spark_orm::Spark;
 use spark_orm_derive::Model;
 use std::fmt::Debug;
@@ -17,6 +24,13 @@ struct User {
 	collect: Product,
 }
 
+#[Model(coll_name = "profiles")]
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Profile {
+	name: Option<String>,
+	bio: Option<String>,
+}
+
 //TODO test From trait with struct that has generic
 #[derive(Serialize, Deserialize, Default, Debug)]
 struct Product {
@@ -31,16 +45,178 @@ async fn save() {
 	user_model.save(None).await.unwrap();
 }
 
+#[tokio::test]
+async fn save_sets_equal_timestamps_on_insert() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Hossein".to_string();
+	let id = user_model.save(None).await.unwrap().into_id();
+
+	let stored = user_model.fetch(doc! { "_id": id }, None).await.unwrap().unwrap();
+	assert!(stored.created_at.is_some());
+	assert_eq!(stored.created_at, stored.updated_at);
+}
+
+#[tokio::test]
+async fn save_with_unknown_id_does_not_insert() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	let id = ObjectId::new();
+	user_model._id = Some(id);
+	user_model.name = "Ghost".to_string();
+
+	let error = user_model.save(None).await.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+	assert!(!user_model.exists(doc! { "_id": id }).await.unwrap());
+}
+
+#[tokio::test]
+async fn latest_and_oldest() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "dated_users");
+	user_model.delete_many(doc! {}, None).await.unwrap();
+	for name in ["First", "Second", "Third"] {
+		let mut created_model = spark_orm::model::Model::<User>::new(Some(&db), "dated_users");
+		created_model.name = name.to_string();
+		created_model.save(None).await.unwrap();
+	}
+
+	user_model.latest(doc! {}).await.unwrap().unwrap();
+	assert_eq!(user_model.name, "Third");
+	user_model.oldest(doc! {}).await.unwrap().unwrap();
+	assert_eq!(user_model.name, "First");
+	user_model.latest(doc! { "name": { "$ne": "Third" } }).await.unwrap().unwrap();
+	assert_eq!(user_model.name, "Second");
+	assert!(user_model.latest(doc! { "name": "Nobody" }).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn refresh() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Stale".to_string();
+	user_model.save(None).await.unwrap();
+	let id = user_model._id.unwrap();
+
+	user_model.update(doc! { "_id": id }, doc! { "$set": { "age": 33 } }, None).await.unwrap();
+	assert!(user_model.refresh().await.unwrap());
+	assert_eq!(user_model.age, 33);
+
+	user_model.delete(doc! { "_id": id }, None).await.unwrap();
+	assert!(!user_model.refresh().await.unwrap());
+}
+
+#[tokio::test]
+async fn refresh_without_id() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	let error = user_model.refresh().await.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn ping() {
+	get_db().await;
+	assert!(Spark::ping().await.unwrap() < std::time::Duration::from_secs(2));
+	assert!(Spark::is_connected().await);
+}
+
+#[tokio::test]
+async fn database_by_name() {
+	let db = get_db().await;
+	let tenant = Spark::database("tenant_42");
+	assert_eq!(tenant.name(), "tenant_42");
+	assert!(Arc::ptr_eq(&tenant, &Spark::database("tenant_42")));
+	assert!(Arc::ptr_eq(&db, &Spark::database(db.name())));
+
+	let mut user_model = User::new_model(Some(&tenant));
+	user_model.name = "Tenant".to_string();
+	user_model.save(None).await.unwrap();
+	assert!(!User::new_model(Some(&db)).exists(doc! { "_id": user_model._id }).await.unwrap());
+}
+
+#[tokio::test]
+async fn save_typed() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Hossein".to_string();
+	let id: ObjectId = user_model.save_typed().await.unwrap();
+
+	let error = user_model.save_typed::<String>().await.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+	println!("The saved id {id}");
+}
+
+#[tokio::test]
+async fn find_by_id() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Hossein".to_string();
+	let id: ObjectId = user_model.save_typed().await.unwrap();
+
+	let mut loaded = User::new_model(Some(&db));
+	let found = loaded.find_by_id_str(&id.to_hex(), None).await.unwrap().unwrap();
+	assert_eq!(found._id, Some(id));
+}
+
+#[tokio::test]
+async fn find_by_id_rejects_malformed_id() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	let error = user_model.find_by_id_str("not an id", None).await.unwrap_err();
+
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn save_retrying() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Hossein".to_string();
+	user_model.save_retrying(None).await.unwrap();
+}
+
 #[tokio::test]
 async fn find_one() {
 	let db = get_db().await;
 	let mut user_model = User::new_model(Some(&db));
-	let mut sample = User::default();
-	sample.name = "Hossein".to_string();
+	let sample = User {
+		name: "Hossein".to_string(),
+		..Default::default()
+	};
 	let founded = user_model.find_one(sample, None).await.unwrap();
 	println!("The founded object {:?} ", founded);
 }
 
+#[tokio::test]
+async fn fetch_keeps_the_model() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Not saved".to_string();
+	let fetched = user_model.fetch(doc! { "name": "Hossein" }, None).await.unwrap();
+	println!("The fetched object {:?} ", fetched);
+	assert_eq!(user_model.name, "Not saved");
+}
+
+#[tokio::test]
+async fn find_one_sorted() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	let founded = user_model
+		.find_one_sorted(doc! { "name": "Hossein" }, doc! { "age": -1 }, None)
+		.await
+		.unwrap();
+	println!("The oldest Hossein {:?} ", founded);
+}
+
+#[tokio::test]
+async fn find_one_in_natural_order() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	let founded = user_model.find_one_sorted(doc! {}, NaturalOrder::Reverse, None).await.unwrap();
+	println!("The last inserted {:?} ", founded);
+}
+
 #[tokio::test]
 async fn update() {
 	let db = get_db().await;
@@ -54,7 +230,7 @@ async fn update() {
 #[tokio::test]
 async fn update_with_doc() {
 	let db = get_db().await;
-	let user_model = User::new_model(Some(&db));
+	let mut user_model = User::new_model(Some(&db));
 	let updated = user_model
 		.update(
 			doc! {
@@ -73,11 +249,271 @@ async fn update_with_doc() {
 }
 
 #[tokio::test]
-async fn update_with_model() {
+async fn update_pipeline() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let updated = user_model
+		.update_pipeline(
+			doc! {
+				"name": "Hossein",
+			},
+			vec![doc! {
+				"$set": {
+					"age": { "$add": ["$age", 1] }
+				}
+			}],
+			None,
+		)
+		.await
+		.unwrap();
+	println!("The Updated info {:?}", updated);
+}
+
+#[tokio::test]
+async fn batch_update_rejects_replacement() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let result = user_model
+		.batch_update(vec![
+			(doc! { "name": "Hossein" }, doc! { "$set": { "age": 20 } }),
+			(doc! { "name": "Hossein 2" }, doc! { "age": 21 }),
+		])
+		.await;
+
+	let error = result.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn bulk_write() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "bulk_users");
+	user_model.delete_many(doc! {}, None).await.unwrap();
+	user_model.name = "Changed".to_string();
+	user_model.save(None).await.unwrap();
+	let changed_id = user_model._id.unwrap();
+
+	let result = user_model
+		.bulk_write(
+			vec![
+				WriteOp::Insert(User {
+					name: "Removed".to_string(),
+					..Default::default()
+				}),
+				WriteOp::UpdateOne {
+					filter: doc! { "_id": changed_id },
+					update: doc! { "$set": { "age": 30 } },
+				},
+				WriteOp::DeleteOne(doc! { "name": "Removed" }),
+				WriteOp::Insert(User {
+					name: "Kept".to_string(),
+					..Default::default()
+				}),
+			],
+			None,
+		)
+		.await
+		.unwrap();
+
+	assert_eq!(result.inserted_count, 2);
+	assert_eq!(result.matched_count, 1);
+	assert_eq!(result.modified_count, 1);
+	assert_eq!(result.deleted_count, 1);
+}
+
+#[tokio::test]
+async fn bulk_write_rejects_replacement() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let result = user_model
+		.bulk_write(
+			vec![
+				WriteOp::DeleteOne(doc! { "name": "Hossein" }),
+				WriteOp::UpdateOne {
+					filter: doc! { "name": "Hossein 2" },
+					update: doc! { "age": 21 },
+				},
+			],
+			BulkWriteOptions::unordered(),
+		)
+		.await;
+
+	let error = result.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn save_many() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db)).without_observers();
+	let users = (0..3)
+		.map(|i| User {
+			name: format!("Seed {i}"),
+			..Default::default()
+		})
+		.collect::<Vec<User>>();
+
+	let ids = user_model.save_many(users, None).await.unwrap();
+
+	assert_eq!(ids.len(), 3);
+}
+
+#[tokio::test]
+async fn save_many_empty_is_noop() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+
+	let ids = user_model.save_many(Vec::new(), None).await.unwrap();
+
+	assert!(ids.is_empty());
+}
+
+#[tokio::test]
+async fn find_stream() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let mut users = user_model.find_stream(doc! {}, None).await.unwrap();
+	while let Some(user) = users.next().await {
+		println!("The user {:?}", user.unwrap());
+	}
+}
+
+#[tokio::test]
+async fn paginate() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let page = user_model.paginate(doc! {}, 1, 2).await.unwrap();
+
+	assert!(page.items.len() <= 2);
+	assert_eq!(page.total_pages, page.total.div_ceil(2));
+}
+
+#[tokio::test]
+async fn paginate_rejects_page_zero() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let error = user_model.paginate(doc! {}, 0, 20).await.unwrap_err();
+
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn count_and_exists() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let total = user_model.count(doc! {}, None).await.unwrap();
+	let exists = user_model.exists(doc! {}).await.unwrap();
+
+	assert_eq!(exists, total > 0);
+}
+
+#[tokio::test]
+async fn find_one_and_update() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	let options = mongodb::options::FindOneAndUpdateOptions::builder()
+		.return_document(mongodb::options::ReturnDocument::After)
+		.build();
+	let updated = user_model
+		.find_one_and_update(doc! { "name": "Hossein" }, doc! { "$inc": { "age": 1 } }, options)
+		.await
+		.unwrap();
+
+	if let Some(user) = updated {
+		assert!(user.updated_at.is_some());
+	}
+}
+
+#[tokio::test]
+async fn delete_many_and_find_one_and_delete() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Temporary".to_string();
+	user_model.save(None).await.unwrap();
+
+	let deleted = user_model.find_one_and_delete(doc! { "name": "Temporary" }, None).await.unwrap();
+	assert_eq!(deleted.unwrap().name, "Temporary");
+
+	let count = user_model.delete_many(doc! { "name": "Seed 0" }, None).await.unwrap();
+	println!("Deleted {count} seeded users");
+}
+
+#[tokio::test]
+async fn aggregate() {
+	#[derive(Deserialize, Debug)]
+	struct AgeGroup {
+		_id: u64,
+		count: u64,
+	}
+
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let groups: Vec<AgeGroup> = user_model
+		.aggregate(vec![doc! { "$group": { "_id": "$age", "count": { "$sum": 1 } } }], None)
+		.await
+		.unwrap();
+
+	assert!(groups.iter().all(|group| group.count > 0));
+}
+
+#[tokio::test]
+async fn register_attributes() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	user_model.register_attributes(vec![("name", IndexType::Asc)]).await.unwrap();
+}
+
+#[tokio::test]
+async fn register_indexes() {
+	let db = get_db().await;
+	let user_model = spark_orm::model::Model::<User>::new(Some(&db), "users_indexed");
+	user_model
+		.register_indexes(vec![
+			IndexSpec::new("name", IndexType::Asc).unique().name("unique_name"),
+			IndexSpec::compound(vec![("collect.name", IndexType::Asc), ("age", IndexType::Desc)]),
+		])
+		.await
+		.unwrap();
+}
+
+#[tokio::test]
+async fn register_ttl() {
+	let db = get_db().await;
+	let user_model = spark_orm::model::Model::<User>::new(Some(&db), "users_sessions");
+	user_model.register_ttl("created_at", std::time::Duration::from_secs(1800)).await.unwrap();
+	user_model.register_attributes(vec![("name", IndexType::Asc)]).await.unwrap();
+}
+
+#[tokio::test]
+async fn register_ttl_rejects_non_date_field() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let error =
+		user_model.register_ttl("name", std::time::Duration::from_secs(60)).await.unwrap_err();
+
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn distinct() {
 	let db = get_db().await;
 	let user_model = User::new_model(Some(&db));
-	let mut sample_user = User::default();
-	sample_user.name = "Hossein 33".to_string();
+	let names: Vec<String> = user_model.distinct("name", None, None).await.unwrap();
+	let mut unique = names.clone();
+	unique.sort();
+	unique.dedup();
+
+	assert_eq!(names.len(), unique.len());
+}
+
+#[tokio::test]
+async fn update_with_model() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	let sample_user = User {
+		name: "Hossein 33".to_string(),
+		..Default::default()
+	};
 	let updated = user_model
 		.update(
 			&sample_user,
@@ -94,6 +530,16 @@ async fn update_with_model() {
 	println!("The Updated info {:?}", updated);
 }
 
+#[tokio::test]
+async fn model_try_into_document() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Hossein".to_string();
+	let document = Document::try_from(&user_model).unwrap();
+
+	assert_eq!(document.get_str("name"), Ok("Hossein"));
+}
+
 #[tokio::test]
 async fn update_with_model_instance() {
 	let db = get_db().await;
@@ -102,7 +548,7 @@ async fn update_with_model_instance() {
 	user_model.age = 58;
 	let updated = user_model
 		.update(
-			&user_model,
+			Document::try_from(&user_model).unwrap(),
 			doc! {
 				"$set": {
 					"name": "Hossein 325"
@@ -125,6 +571,416 @@ async fn find_and_collect() {
 	println!("The users {users:?} ")
 }
 
+#[tokio::test]
+async fn insert_many_in_batches() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db)).with_batching(InsertBatching {
+		max_count: 2,
+		..Default::default()
+	});
+	let users = (0..5).map(|age| User {
+		name: "Batched".to_string(),
+		age,
+		..Default::default()
+	});
+
+	let inserted = user_model.insert_many(users, None).await.unwrap();
+
+	let mut indexes = inserted.inserted_ids.keys().copied().collect::<Vec<usize>>();
+	indexes.sort();
+	assert_eq!(indexes, vec![0, 1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn recent() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+
+	let users = user_model.recent(3).await.unwrap();
+
+	assert!(users.len() <= 3);
+	assert!(users.windows(2).all(|pair| pair[0].created_at >= pair[1].created_at));
+}
+
+#[tokio::test]
+async fn find_resilient_stream() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+
+	let users = user_model
+		.find_resilient_stream(doc! { "name": "Hossein" }, "_id")
+		.collect::<Vec<_>>()
+		.await;
+
+	assert!(users.iter().all(|user| user.is_ok()));
+}
+
+#[tokio::test]
+async fn find_elem_match() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+
+	let users = user_model
+		.find_elem_match("tags", doc! { "kind": "admin", "level": { "$gt": 2 } }, None)
+		.await
+		.unwrap();
+
+	println!("The users {users:?} ")
+}
+
+#[tokio::test]
+async fn graph_lookup() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+
+	let users = user_model
+		.graph_lookup(Bson::from("$name"), "name", "collect.name", "hierarchy", Some(2))
+		.await
+		.unwrap();
+
+	assert!(users.iter().all(|user| user.get_array("hierarchy").is_ok()));
+}
+
+#[tokio::test]
+async fn export_and_import_bson() {
+	let db = get_db().await;
+	db.collection::<mongodb::bson::Document>("users_restore").drop(None).await.unwrap();
+	let user_model = User::new_model(Some(&db));
+	let restore_model = spark_orm::model::Model::<User>::new(Some(&db), "users_restore");
+
+	let mut backup = Vec::new();
+	let exported = user_model.export_bson(doc! { "name": "Hossein" }, &mut backup).await.unwrap();
+	let imported = restore_model.import_bson(backup.as_slice()).await.unwrap();
+
+	assert_eq!(exported, imported);
+}
+
+#[tokio::test]
+async fn schema_audit() {
+	let db = get_db().await;
+	let drifted = db.collection::<mongodb::bson::Document>("users_drifted");
+	drifted.drop(None).await.unwrap();
+	drifted
+		.insert_one(doc! { "name": "Hossein", "age": "twenty", "nickname": "H" }, None)
+		.await
+		.unwrap();
+	let model = spark_orm::model::Model::<User>::new(Some(&db), "users_drifted");
+
+	let report = model.schema_audit(10).await.unwrap();
+
+	assert_eq!(report.sampled, 1);
+	assert_eq!(report.failures[0].fields, vec!["age".to_string()]);
+}
+
+#[tokio::test]
+async fn read_snapshot() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+
+	let (users, hossein) = Spark::read_snapshot(|session| {
+		async move {
+			let users = user_model.find_and_collect_with_session(doc! {}, None, session).await?;
+			let hossein =
+				user_model.fetch_with_session(doc! { "name": "Hossein" }, None, session).await?;
+			Ok((users, hossein))
+		}
+		.boxed()
+	})
+	.await
+	.unwrap();
+
+	println!("The users {users:?} and {hossein:?}")
+}
+
+#[tokio::test]
+async fn transaction() {
+	let db = get_db().await;
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Buyer".to_string();
+	let mut seller_model = spark_orm::model::Model::<User>::new(Some(&db), "sellers");
+
+	let mut user_model = Spark::transaction(move |session| {
+		async move {
+			user_model.save_with_session(session, None).await?;
+			seller_model
+				.update_with_session(
+					doc! { "name": "Seller" },
+					doc! { "$inc": { "age": 1 } },
+					None,
+					session,
+				)
+				.await?;
+			Ok(user_model)
+		}
+		.boxed()
+	})
+	.await
+	.unwrap();
+
+	user_model.dispatch_committed().await.unwrap();
+}
+
+#[tokio::test]
+async fn nested_transaction_is_rejected() {
+	get_db().await;
+	let error = Spark::transaction(|_| {
+		async {
+			let inner = Spark::transaction(|_| async { Ok(()) }.boxed()).await;
+			assert!(matches!(SparkError::from(inner.unwrap_err()), SparkError::NestedTransaction));
+			Err::<(), _>(SparkError::NestedTransaction.into())
+		}
+		.boxed()
+	})
+	.await
+	.unwrap_err();
+	// the outer transaction is aborted with the error of its closure
+	assert!(matches!(SparkError::from(error), SparkError::NestedTransaction));
+}
+
+#[tokio::test]
+async fn watch() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let pipeline = vec![doc! { "$match": { "operationType": "insert" } }];
+	let mut changes = user_model.watch(pipeline, None).await.unwrap();
+
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Watched".to_string();
+	let id = user_model.save(None).await.unwrap().into_id();
+
+	let change = changes.next().await.unwrap().unwrap();
+	assert_eq!(change.operation, OperationType::Insert);
+	assert_eq!(change.full_document.unwrap().name, "Watched");
+	assert_eq!(change.document_key, Some(doc! { "_id": id }));
+}
+
+#[tokio::test]
+async fn patch() {
+	let db = get_db().await;
+	let mut profile_model = Profile::new_model(Some(&db));
+	profile_model.name = Some("Patched".to_string());
+	profile_model.bio = Some("Before".to_string());
+	let id = profile_model.save(None).await.unwrap().into_id();
+
+	let changes = Profile {
+		bio: Some("After".to_string()),
+		..Default::default()
+	};
+	let result = profile_model.patch(doc! { "_id": &id }, changes).await.unwrap();
+	assert_eq!(result.matched_count, 1);
+
+	let mut found_model = Profile::new_model(Some(&db));
+	let found = found_model.find_one(doc! { "_id": &id }, None).await.unwrap().unwrap();
+	// the None name isn't patched
+	assert_eq!(found.name.as_deref(), Some("Patched"));
+	assert_eq!(found.bio.as_deref(), Some("After"));
+	assert!(found.created_at.is_some());
+	assert!(found.updated_at >= found.created_at);
+}
+
+#[tokio::test]
+async fn query() {
+	let db = get_db().await;
+	let user_model = User::new_model(Some(&db));
+	let users = user_model
+		.query()
+		.filter(doc! { "age": { "$gte": 0 } })
+		.sort_desc("age")
+		.sort_asc("name")
+		.project(&["name", "age", "collect", "-_id"])
+		.skip(1)
+		.limit(5)
+		.collect()
+		.await
+		.unwrap();
+	assert!(users.len() <= 5);
+	assert!(users.windows(2).all(|pair| pair[0].age >= pair[1].age));
+}
+
+#[tokio::test]
+async fn query_rejects_mixed_projection() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let error = user_model.query().project(&["name", "-age"]).collect().await.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn duplicate_key_is_a_spark_error() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "unique_users");
+	user_model.register_indexes(vec![IndexSpec::new("name", IndexType::Asc).unique()]).await.unwrap();
+	user_model.delete_many(doc! {}, None).await.unwrap();
+	user_model.name = "Taken".to_string();
+	user_model.save(None).await.unwrap();
+
+	let mut other_model = spark_orm::model::Model::<User>::new(Some(&db), "unique_users");
+	other_model.name = "Taken".to_string();
+	let error = SparkError::from(other_model.save(None).await.unwrap_err());
+	match error {
+		SparkError::Duplicate { key, .. } => assert!(key.contains("Taken")),
+		other => panic!("expected a duplicate key error , got {other:?}"),
+	}
+}
+
+#[derive(Deserialize, Debug)]
+struct UserName {
+	_id: Option<ObjectId>,
+	name: String,
+}
+
+#[tokio::test]
+async fn find_projected() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "projected_users");
+	user_model.delete_many(doc! {}, None).await.unwrap();
+	user_model.name = "Projected".to_string();
+	user_model.age = 40;
+	user_model.save(None).await.unwrap();
+
+	let names: Vec<UserName> = user_model.find_projected(doc! {}, &["name"]).await.unwrap();
+	assert_eq!(names.len(), 1);
+	assert_eq!(names[0].name, "Projected");
+	assert_eq!(names[0]._id, user_model._id);
+
+	let names: Vec<UserName> = user_model.find_projected(doc! {}, &["name", "-_id"]).await.unwrap();
+	assert!(names[0]._id.is_none());
+}
+
+#[tokio::test]
+async fn find_projected_rejects_mixed_projection() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let error = user_model
+		.find_projected::<UserName>(doc! {}, &["name", "-age"])
+		.await
+		.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn for_each_batch() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "batched_users");
+	user_model.delete_many(doc! {}, None).await.unwrap();
+	for age in 0..5 {
+		let mut user = spark_orm::model::Model::<User>::new(Some(&db), "batched_users");
+		user.name = format!("Batched {age}");
+		user.age = age;
+		user.save(None).await.unwrap();
+	}
+
+	let mut sizes = Vec::new();
+	user_model
+		.for_each_batch(doc! {}, 2, |users| {
+			sizes.push(users.len());
+			async { Ok(()) }
+		})
+		.await
+		.unwrap();
+	assert_eq!(sizes, vec![2, 2, 1]);
+
+	// the error of the callback stops the batches
+	let mut calls = 0;
+	let error = user_model
+		.for_each_batch(doc! {}, 2, |_| {
+			calls += 1;
+			async { Err(spark_orm::error::Error::new("The export failed").into()) }
+		})
+		.await
+		.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+	assert_eq!(calls, 1);
+}
+
+#[tokio::test]
+async fn for_each_batch_rejects_empty_batches() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let user_model = User::new_model(Some(&db));
+	let error = user_model.for_each_batch(doc! {}, 0, |_| async { Ok(()) }).await.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn find_ci() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "ci_users");
+	user_model
+		.register_indexes(vec![IndexSpec::new("name", IndexType::Asc)
+			.unique()
+			.collation(case_insensitive())])
+		.await
+		.unwrap();
+	user_model.delete_many(doc! {}, None).await.unwrap();
+
+	user_model.name = "John@Mail.com".to_string();
+	user_model.save(None).await.unwrap();
+
+	// the unique index with strength 2 rejects the same name in another case
+	let mut duplicate_model = spark_orm::model::Model::<User>::new(Some(&db), "ci_users");
+	duplicate_model.name = "john@mail.com".to_string();
+	assert!(duplicate_model.save(None).await.is_err());
+
+	let users = user_model.find_ci("name", "JOHN@MAIL.COM").await.unwrap();
+	assert_eq!(users.len(), 1);
+}
+
+#[tokio::test]
+async fn search() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "searched_users");
+	user_model.register_text_index(vec!["name"], None).await.unwrap();
+	// the text index survives the reconciliation of the other indexes
+	user_model
+		.register_indexes(vec![IndexSpec::new("age", IndexType::Asc)])
+		.await
+		.unwrap();
+	user_model.register_text_index(vec!["name"], None).await.unwrap();
+
+	user_model.name = "wireless headphones".to_string();
+	user_model.save(None).await.unwrap();
+
+	let scored = user_model.search_scored("headphones", None).await.unwrap();
+	assert!(!scored.is_empty());
+	assert!(scored.iter().all(|(_, score)| *score > 0.0));
+	assert!(scored.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+	let users = user_model.search("headphones", None).await.unwrap();
+	assert_eq!(users.len(), scored.len());
+}
+
+#[tokio::test]
+async fn upsert() {
+	let db = get_db().await;
+	let mut user_model = spark_orm::model::Model::<User>::new(Some(&db), "upserted_users");
+	user_model.delete_many(doc! {}, None).await.unwrap();
+
+	user_model.name = "Natural".to_string();
+	user_model.age = 20;
+	let inserted = user_model.upsert(doc! { "name": "Natural" }, None).await.unwrap();
+	assert!(inserted.is_inserted());
+
+	let mut other_model = spark_orm::model::Model::<User>::new(Some(&db), "upserted_users");
+	other_model.name = "Natural".to_string();
+	other_model.age = 21;
+	let updated = other_model.upsert(doc! { "name": "Natural" }, None).await.unwrap();
+	assert!(!updated.is_inserted());
+	assert_eq!(updated.id(), inserted.id());
+
+	let mut found_model = spark_orm::model::Model::<User>::new(Some(&db), "upserted_users");
+	let found = found_model.find_one(doc! { "name": "Natural" }, None).await.unwrap().unwrap();
+	assert_eq!(found.age, 21);
+	// the second upsert doesn't change the creation time
+	assert_eq!(found.created_at, user_model.created_at);
+}
+
+#[tokio::test]
+async fn warmup() {
+	get_db().await;
+	Spark::warmup(4).await.unwrap();
+}
+
+#[allow(dead_code)]
 async fn borrow_inner() {
 	todo!()
 }
@@ -0,0 +1,80 @@
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use spark_orm::futures::future::BoxFuture;
+use spark_orm::futures::FutureExt;
+use spark_orm::model::migration::{run_migrations, Migration, MIGRATIONS_COLLECTION};
+use spark_orm::model::MongodbResult;
+use spark_orm::Spark;
+use std::sync::Arc;
+
+/// inserts a document named after the migration , or fails when `fail` is set
+struct Seed {
+	name: &'static str,
+	fail: bool,
+}
+
+impl Migration for Seed {
+	fn name(&self) -> &str {
+		self.name
+	}
+
+	fn up<'a>(&'a self, db: &'a Database) -> BoxFuture<'a, MongodbResult<()>> {
+		async move {
+			if self.fail {
+				return Err(spark_orm::error::Error::new("The migration failed").into());
+			}
+			db.collection::<Document>("migrated")
+				.insert_one(doc! { "by": self.name }, None)
+				.await?;
+			Ok(())
+		}
+		.boxed()
+	}
+}
+
+fn seed(name: &'static str, fail: bool) -> Box<dyn Migration> {
+	Box::new(Seed {
+		name,
+		fail,
+	})
+}
+
+#[tokio::test]
+async fn migrations_run_once_in_order() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "migrations_db").await);
+	db.collection::<Document>(MIGRATIONS_COLLECTION).delete_many(doc! {}, None).await.unwrap();
+	db.collection::<Document>("migrated").delete_many(doc! {}, None).await.unwrap();
+
+	// the failed migration stops the run , the later one stays pending
+	let error =
+		run_migrations(&db, vec![seed("first", false), seed("second", true), seed("third", false)])
+			.await
+			.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+
+	let applied = run_migrations(
+		&db,
+		vec![seed("first", false), seed("second", false), seed("third", false)],
+	)
+	.await
+	.unwrap();
+	assert_eq!(applied, vec!["second".to_string(), "third".to_string()]);
+
+	let applied = run_migrations(
+		&db,
+		vec![seed("first", false), seed("second", false), seed("third", false)],
+	)
+	.await
+	.unwrap();
+	assert!(applied.is_empty());
+	let migrated = db.collection::<Document>("migrated").count_documents(None, None).await.unwrap();
+	assert_eq!(migrated, 3);
+}
+
+#[tokio::test]
+async fn duplicated_migration_names_are_rejected() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "migrations_db").await);
+	let error =
+		run_migrations(&db, vec![seed("first", false), seed("first", false)]).await.unwrap_err();
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
@@ -1,9 +1,11 @@
-use mongodb::bson::doc;
+use mongodb::bson::{doc, Document};
 use serde::{Deserialize, Serialize};
-use spark_orm::model::observer::Observer;
+use spark_orm::model::observer::{Observer, ObserverContext};
 use spark_orm::model::{Model, MongodbResult};
+use spark_orm::types::SaveOutcome;
 use spark_orm::Spark;
 use spark_orm_derive::Model;
+use std::sync::Arc;
 
 // implement by adding observer to Model
 #[Model(coll_name = "users", observer)]
@@ -33,11 +35,11 @@ impl Observer<Person> for Person {
         Ok(())
     }
 
-    async fn updated(model: &mut Model<'_, Person>) -> MongodbResult<()> {
+    async fn updated(_model: &mut Model<'_, Person>) -> MongodbResult<()> {
         Ok(())
     }
 
-    async fn deleted(model: &mut Model<'_, Person>) -> MongodbResult<()> {
+    async fn deleted(_model: &mut Model<'_, Person>) -> MongodbResult<()> {
         Ok(())
     }
 }
@@ -45,7 +47,7 @@ impl Observer<Person> for Person {
 impl Observer<User> for User {
     async fn created(model: &mut Model<'_, User>) -> MongodbResult<()> {
         let mut person_model = Person::new_model(None);
-        if model.name == "Hello".to_string() {
+        if model.name == "Hello" {
             model.name = "Something".to_string();
             model.save(None).await?;
         }
@@ -55,6 +57,216 @@ impl Observer<User> for User {
     }
 }
 
+#[Model(coll_name = "accounts", observer)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Account {
+    password: String,
+}
+
+impl Observer<Account> for Account {
+    async fn creating(model: &mut Model<'_, Account>) -> MongodbResult<()> {
+        model.password = format!("hashed:{}", model.password);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn creating_changes_are_saved() {
+    connect_db().await;
+    let mut account_model = Account::new_model(None);
+    account_model.password = "secret".to_string();
+    let id = account_model.save(None).await.unwrap().into_id();
+
+    let stored = Account::new_model(None).fetch(doc! { "_id": id }, None).await.unwrap().unwrap();
+    assert_eq!(stored.password, "hashed:secret");
+}
+
+#[Model(coll_name = "invites", observer)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Invite {
+    email: String,
+    accepted: bool,
+}
+
+impl Observer<Invite> for Invite {
+    async fn saving(model: &mut Model<'_, Invite>) -> MongodbResult<()> {
+        if model.email.is_empty() {
+            return Err(spark_orm::error::Error::new("The email is required").into());
+        }
+        Ok(())
+    }
+
+    async fn deleting(model: &mut Model<'_, Invite>) -> MongodbResult<()> {
+        if model.accepted {
+            return Err(spark_orm::error::Error::new("An accepted invite can't be deleted").into());
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn saving_error_aborts_save() {
+    let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+    let mut invite_model = Invite::new_model(Some(&db));
+    let error = invite_model.save(None).await.unwrap_err();
+
+    assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+    assert!(invite_model.created_at.is_none());
+}
+
+#[tokio::test]
+async fn deleting_error_aborts_delete() {
+    let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+    let mut invite_model = Invite::new_model(Some(&db));
+    invite_model.accepted = true;
+    let error = invite_model.delete(doc! {}, None).await.unwrap_err();
+
+    assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[Model(coll_name = "articles", observer)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Article {
+    title: String,
+    body: String,
+    #[serde(skip)]
+    changed: Vec<String>,
+    #[serde(skip)]
+    previous_title: Option<String>,
+}
+
+impl Observer<Article> for Article {
+    async fn updated_with_context(
+        model: &mut Model<'_, Article>,
+        context: &ObserverContext,
+    ) -> MongodbResult<()> {
+        model.changed = context.changed_fields().keys().cloned().collect();
+        model.previous_title =
+            context.previous_value("title").and_then(|title| title.as_str()).map(str::to_string);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn updated_gets_the_changed_fields() {
+    connect_db().await;
+    let mut article_model = Article::new_model(None);
+    article_model.title = "Draft".to_string();
+    article_model.body = "Text".to_string();
+    article_model.save(None).await.unwrap();
+    assert!(article_model.changed.is_empty());
+
+    article_model.title = "Published".to_string();
+    article_model.save(None).await.unwrap();
+
+    assert!(article_model.changed.contains(&"title".to_string()));
+    assert!(!article_model.changed.contains(&"body".to_string()));
+    assert_eq!(article_model.previous_title.as_deref(), Some("Draft"));
+}
+
+#[Model(coll_name = "ledgers", observer)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Ledger {
+    owner: String,
+    #[serde(skip)]
+    created: u32,
+    #[serde(skip)]
+    updated: u32,
+}
+
+impl Observer<Ledger> for Ledger {
+    async fn created(model: &mut Model<'_, Ledger>) -> MongodbResult<()> {
+        model.created += 1;
+        Ok(())
+    }
+
+    async fn updated(model: &mut Model<'_, Ledger>) -> MongodbResult<()> {
+        model.updated += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn unchanged_save_is_updated() {
+    connect_db().await;
+    let mut ledger_model = Ledger::new_model(None);
+    ledger_model.delete_many(doc! {}, None).await.unwrap();
+    ledger_model.owner = "Kept".to_string();
+    let created = ledger_model.save(None).await.unwrap();
+    assert!(created.is_created());
+
+    // nothing but the timestamp changes , the document is matched and still updated
+    let updated = ledger_model.save(None).await.unwrap();
+    assert_eq!(updated, SaveOutcome::Updated(created.id().clone()));
+    let updated = ledger_model.save(None).await.unwrap();
+    assert_eq!(updated.id(), created.id());
+
+    assert_eq!((ledger_model.created, ledger_model.updated), (1, 2));
+    assert_eq!(ledger_model.count(doc! {}, None).await.unwrap(), 1);
+}
+
+#[Model(coll_name = "members", observer)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Member {
+    name: String,
+    password: String,
+    #[serde(skip)]
+    locked: bool,
+}
+
+impl Observer<Member> for Member {
+    async fn updating(model: &mut Model<'_, Member>) -> MongodbResult<()> {
+        if model.locked {
+            return Err(spark_orm::error::Error::new("The member is locked").into());
+        }
+        Ok(())
+    }
+
+    async fn updating_with_query(
+        model: &mut Model<'_, Member>,
+        _query: &Document,
+        update: &mut Document,
+    ) -> MongodbResult<()> {
+        if let Ok(set) = update.get_document_mut("$set") {
+            if let Ok(password) = set.get_str("password") {
+                let hashed = format!("hashed:{password}");
+                set.insert("password", hashed);
+            }
+        }
+        Self::updating(model).await
+    }
+}
+
+#[tokio::test]
+async fn updating_can_abort_update() {
+    let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+    let mut member_model = Member::new_model(Some(&db));
+    member_model.locked = true;
+    let error = member_model
+        .update(doc! { "name": "Locked" }, doc! { "$set": { "password": "secret" } }, None)
+        .await
+        .unwrap_err();
+
+    assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+#[tokio::test]
+async fn updating_changes_the_update() {
+    connect_db().await;
+    let mut member_model = Member::new_model(None);
+    member_model.delete_many(doc! {}, None).await.unwrap();
+    member_model.name = "Hashed".to_string();
+    member_model.save(None).await.unwrap();
+
+    member_model
+        .update(doc! { "name": "Hashed" }, doc! { "$set": { "password": "secret" } }, None)
+        .await
+        .unwrap();
+    let stored =
+        Member::new_model(None).fetch(doc! { "name": "Hashed" }, None).await.unwrap().unwrap();
+    assert_eq!(stored.password, "hashed:secret");
+}
+
 #[tokio::test]
 async fn save() {
     connect_db().await;
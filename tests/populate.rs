@@ -0,0 +1,60 @@
+use mongodb::bson::doc;
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::Arc;
+
+#[Model(coll_name = "populate_users")]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+struct User {
+	name: String,
+}
+
+#[Model(coll_name = "populate_orders")]
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+struct Order {
+	sku: String,
+	user_id: Option<ObjectId>,
+	watcher_ids: Vec<ObjectId>,
+}
+
+#[tokio::test]
+async fn populate_batches_references() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.name = "Buyer".to_string();
+	let buyer_id = user_model.save(None).await.unwrap().id().as_object_id().unwrap();
+
+	let missing_id = ObjectId::new();
+	let orders = vec![
+		Order {
+			sku: "A1".to_string(),
+			user_id: Some(buyer_id),
+			watcher_ids: vec![buyer_id, missing_id],
+			..Default::default()
+		},
+		Order {
+			sku: "B2".to_string(),
+			user_id: Some(missing_id),
+			..Default::default()
+		},
+		Order {
+			sku: "C3".to_string(),
+			..Default::default()
+		},
+	];
+
+	let order_model = Order::new_model(Some(&db));
+	let users = order_model.populate(&orders, "user_id", &user_model).await.unwrap();
+	assert_eq!(users.len(), 3);
+	assert_eq!(users[0].as_ref().map(|user| user.name.as_str()), Some("Buyer"));
+	assert!(users[1].is_none());
+	assert!(users[2].is_none());
+
+	let watchers = order_model.populate_many(&orders, "watcher_ids", &user_model).await.unwrap();
+	assert_eq!(watchers[0].len(), 2);
+	assert!(watchers[0][0].is_some());
+	assert!(watchers[0][1].is_none());
+	assert!(watchers[1].is_empty());
+}
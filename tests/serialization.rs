@@ -0,0 +1,48 @@
+use mongodb::bson::Document;
+use serde::{Deserialize, Serialize, Serializer};
+use spark_orm::model::observer::{GlobalObserver, WriteOp};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SERIALIZED: AtomicUsize = AtomicUsize::new(0);
+
+/// a field that counts how many times the model is serialized
+#[derive(Deserialize, Debug, Default)]
+struct Counted(String);
+
+impl Serialize for Counted {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		SERIALIZED.fetch_add(1, Ordering::SeqCst);
+		serializer.serialize_str(&self.0)
+	}
+}
+
+struct Audit;
+
+impl GlobalObserver for Audit {
+	fn on_write(&self, _collection: &str, _op: WriteOp, _doc: &Document) {}
+}
+
+#[Model(coll_name = "counted_users")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct User {
+	name: Counted,
+}
+
+#[tokio::test]
+async fn save_serializes_the_model_once() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+	// the global observer gets the written document , it must not serialize the model again
+	Spark::set_global_observer(Audit).unwrap();
+	let mut user_model = User::new_model(None);
+	user_model.name = Counted("Once".to_string());
+
+	user_model.save(None).await.unwrap();
+	assert_eq!(SERIALIZED.load(Ordering::SeqCst), 1);
+
+	// the update serializes once too
+	user_model.name = Counted("Twice".to_string());
+	user_model.save(None).await.unwrap();
+	assert_eq!(SERIALIZED.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,100 @@
+use mongodb::bson::{doc, Document};
+use serde::{Deserialize, Serialize};
+use spark_orm::model::observer::{GlobalObserver, WriteOp};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+static DELETES: AtomicUsize = AtomicUsize::new(0);
+
+struct Audit;
+
+impl GlobalObserver for Audit {
+	fn on_write(&self, collection: &str, op: WriteOp, doc: &Document) {
+		if collection == "soft_delete_posts"
+			&& op == WriteOp::Delete
+			&& doc.get_str("title") == Ok("Once")
+		{
+			DELETES.fetch_add(1, Ordering::SeqCst);
+		}
+	}
+}
+
+#[Model(coll_name = "soft_delete_posts", soft_delete)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Post {
+	title: String,
+}
+
+#[Model(coll_name = "soft_delete_comments")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Comment {
+	text: String,
+}
+
+#[tokio::test]
+async fn soft_deleted_post_is_hidden_from_reads() {
+	connect_db().await;
+	let mut post_model = Post::new_model(None);
+	post_model.title = "Draft".to_string();
+	post_model.save(None).await.unwrap();
+
+	let deleted = post_model.soft_delete(doc! { "title": "Draft" }).await.unwrap();
+	assert_eq!(deleted, 1);
+	let found = Post::new_model(None).fetch(doc! { "title": "Draft" }, None).await.unwrap();
+	assert!(found.is_none());
+
+	let trashed =
+		Post::new_model(None).with_trashed().fetch(doc! { "title": "Draft" }, None).await.unwrap();
+	assert!(trashed.unwrap().deleted_at.is_some());
+
+	post_model.restore(doc! { "title": "Draft" }).await.unwrap();
+	let restored = Post::new_model(None).fetch(doc! { "title": "Draft" }, None).await.unwrap();
+	assert!(restored.is_some());
+}
+
+#[tokio::test]
+async fn soft_delete_keeps_the_first_deletion_time() {
+	connect_db().await;
+	let mut post_model = Post::new_model(None);
+	post_model.delete_many(doc! { "title": "Twice" }, None).await.unwrap();
+	post_model.title = "Twice".to_string();
+	post_model.save(None).await.unwrap();
+
+	assert_eq!(post_model.soft_delete(doc! { "title": "Twice" }).await.unwrap(), 1);
+	let first = Post::new_model(None).with_trashed().fetch(doc! { "title": "Twice" }, None).await;
+	let first = first.unwrap().unwrap().deleted_at;
+
+	let mut trashed_model = Post::new_model(None).with_trashed();
+	assert_eq!(trashed_model.soft_delete(doc! { "title": "Twice" }).await.unwrap(), 0);
+	let second = Post::new_model(None).with_trashed().fetch(doc! { "title": "Twice" }, None).await;
+	assert_eq!(second.unwrap().unwrap().deleted_at, first);
+}
+
+#[tokio::test]
+async fn soft_delete_of_a_trashed_document_records_no_write() {
+	connect_db().await;
+	Spark::set_global_observer(Audit).unwrap();
+	let mut post_model = Post::new_model(None);
+	post_model.delete_many(doc! { "title": "Once" }, None).await.unwrap();
+	post_model.title = "Once".to_string();
+	post_model.save(None).await.unwrap();
+
+	assert_eq!(post_model.soft_delete(doc! { "title": "Once" }).await.unwrap(), 1);
+	assert_eq!(post_model.soft_delete(doc! { "title": "Once" }).await.unwrap(), 0);
+	assert_eq!(DELETES.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn soft_delete_needs_the_macro_flag() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut comment_model = Comment::new_model(Some(&db));
+	let error = comment_model.soft_delete(doc! {}).await.unwrap_err();
+
+	assert!(error.get_custom::<spark_orm::error::Error>().is_some());
+}
+
+async fn connect_db() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+}
@@ -0,0 +1,38 @@
+use mongodb::bson::{doc, Bson};
+use serde::{Deserialize, Serialize};
+use spark_orm::model::tenant::TenantScoped;
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+
+#[Model(coll_name = "tenant_users")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct User {
+	name: String,
+	tenant_id: String,
+}
+
+impl TenantScoped for User {
+	fn tenant_field() -> &'static str {
+		"tenant_id"
+	}
+}
+
+#[tokio::test]
+async fn scoped_find() {
+	connect_db().await;
+	let mut user_model = User::new_model(None);
+	user_model.name = "Hossein".to_string();
+	user_model.tenant_id = "tenant_1".to_string();
+	user_model.save(None).await.unwrap();
+
+	let other_tenant = User::new_model(None).scoped(Bson::from("tenant_2"));
+	let users = other_tenant
+		.find_and_collect(doc! { "name": "Hossein", "tenant_id": "tenant_1" }, None)
+		.await
+		.unwrap();
+	assert!(users.is_empty());
+}
+
+async fn connect_db() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+}
@@ -0,0 +1,28 @@
+use mongodb::bson::DateTime;
+use mongodb::options::TimeseriesGranularity;
+use serde::{Deserialize, Serialize};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+
+#[Model(coll_name = "metrics")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Metric {
+	taken_at: Option<DateTime>,
+	sensor: String,
+	value: f64,
+}
+
+#[tokio::test]
+async fn ensure_time_series_is_idempotent() {
+	Spark::global_connect("root", "123", "localhost", "6789", "rm_orm_db").await;
+	let metric_model = Metric::new_model(None);
+
+	metric_model
+		.ensure_time_series("taken_at", Some("sensor"), Some(TimeseriesGranularity::Minutes))
+		.await
+		.unwrap();
+	metric_model
+		.ensure_time_series("taken_at", Some("sensor"), Some(TimeseriesGranularity::Minutes))
+		.await
+		.unwrap();
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use spark_orm::model::util::ModelTimestamps;
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::Arc;
+
+#[Model(coll_name = "camel_users", created_field = "createdAt", updated_field = "updatedAt")]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct User {
+	name: String,
+}
+
+#[tokio::test]
+async fn renamed_timestamp_fields_are_serialized() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.created_at(mongodb::bson::DateTime::now());
+	let document = user_model.inner_to_doc().unwrap();
+
+	assert_eq!(User::CREATED_FIELD, "createdAt");
+	assert_eq!(User::UPDATED_FIELD, "updatedAt");
+	assert!(document.contains_key("createdAt"));
+	assert!(!document.contains_key("created_at"));
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use spark_orm::error::SparkError;
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+
+#[Model(coll_name = "users")]
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct User {
+	name: String,
+}
+
+// the global connection is never set in this test binary
+#[test]
+fn try_new_without_global_connection() {
+	assert!(Spark::try_get_db().is_none());
+	assert!(matches!(User::try_new_model(None), Err(SparkError::NotInitialized)));
+}
+
+#[tokio::test]
+async fn ping_without_global_connection() {
+	let error = Spark::ping().await.unwrap_err();
+	assert!(matches!(SparkError::from(error), SparkError::NotInitialized));
+	assert!(!Spark::is_connected().await);
+}
+
+#[tokio::test]
+async fn warmup_without_global_connection() {
+	let error = Spark::warmup(4).await.unwrap_err();
+	assert!(matches!(SparkError::from(error), SparkError::NotInitialized));
+}
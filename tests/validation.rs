@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use spark_orm::error::SparkError;
+use spark_orm::model::observer::Observer;
+use spark_orm::model::validation::{Validate, ValidationErrors, ValidationOrder};
+use spark_orm::model::{Model, MongodbResult};
+use spark_orm::Spark;
+use spark_orm_derive::Model;
+use std::sync::Arc;
+
+#[Model(coll_name = "validated_users", validate)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct User {
+	email: String,
+	age: u64,
+}
+
+impl Validate for User {
+	fn validate(&self) -> Result<(), ValidationErrors> {
+		let mut errors = ValidationErrors::new();
+		if !self.email.contains('@') {
+			errors.add("email", "The email is invalid");
+		}
+		if self.age < 18 {
+			errors.add("age", "The user must be an adult");
+		}
+		errors.into_result()
+	}
+}
+
+#[tokio::test]
+async fn invalid_model_is_not_saved() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.email = "hossein".to_string();
+
+	let error = user_model.save(None).await.unwrap_err();
+	let errors = error.get_custom::<ValidationErrors>().unwrap();
+	assert_eq!(errors.get("email"), ["The email is invalid"]);
+	assert_eq!(errors.get("age"), ["The user must be an adult"]);
+	assert!(user_model.created_at.is_none() && user_model.updated_at.is_none());
+}
+
+#[tokio::test]
+async fn validation_error_is_a_spark_error() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.email = "hossein@example.com".to_string();
+
+	let error = user_model.save(None).await.unwrap_err();
+	assert_eq!(
+		error.get_custom::<ValidationErrors>().unwrap().get("age"),
+		["The user must be an adult"]
+	);
+	match SparkError::from(error) {
+		SparkError::Validation(errors) => {
+			assert_eq!(errors.get("age"), ["The user must be an adult"]);
+			assert!(errors.get("email").is_empty());
+		}
+		other => panic!("expected a validation error , got {other:?}"),
+	}
+}
+
+#[tokio::test]
+async fn try_save_returns_a_spark_error() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	user_model.email = "hossein@example.com".to_string();
+
+	match user_model.try_save(None).await {
+		Err(SparkError::Validation(errors)) => {
+			assert_eq!(errors.get("age"), ["The user must be an adult"]);
+		}
+		other => panic!("expected a validation error , got {other:?}"),
+	}
+}
+
+#[tokio::test]
+async fn invalid_document_fails_save_many() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut user_model = User::new_model(Some(&db));
+	let users = vec![
+		User {
+			email: "hossein@example.com".to_string(),
+			age: 30,
+			..Default::default()
+		},
+		User::default(),
+	];
+
+	let error = user_model.save_many(users, None).await.unwrap_err();
+	assert!(error.get_custom::<ValidationErrors>().is_some());
+}
+
+#[Model(coll_name = "validated_posts", observer, validate)]
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Post {
+	title: String,
+	slug: String,
+	#[serde(skip)]
+	hooked: bool,
+}
+
+impl Observer<Post> for Post {
+	async fn creating(model: &mut Model<'_, Post>) -> MongodbResult<()> {
+		model.slug = model.title.to_lowercase().replace(' ', "-");
+		model.hooked = true;
+		Ok(())
+	}
+}
+
+impl Validate for Post {
+	fn validate(&self) -> Result<(), ValidationErrors> {
+		let mut errors = ValidationErrors::new();
+		if self.slug.is_empty() {
+			errors.add("slug", "The slug is required");
+		}
+		errors.into_result()
+	}
+}
+
+#[tokio::test]
+async fn validation_runs_before_the_hooks() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut post_model = Post::new_model(Some(&db));
+	post_model.title = "Hello World".to_string();
+
+	// the slug of creating would be valid but the model is checked before it
+	let error = post_model.save(None).await.unwrap_err();
+	assert!(error.get_custom::<ValidationErrors>().is_some());
+	assert!(!post_model.hooked);
+}
+
+#[tokio::test]
+async fn validation_can_run_after_the_hooks() {
+	let db = Arc::new(Spark::connect("root", "123", "localhost", "6789", "rm_orm_db").await);
+	let mut post_model =
+		Post::new_model(Some(&db)).with_validation_order(ValidationOrder::AfterHooks);
+
+	// the empty slug that creating set is validated
+	let error = post_model.save(None).await.unwrap_err();
+	assert_eq!(
+		error.get_custom::<ValidationErrors>().unwrap().get("slug"),
+		["The slug is required"]
+	);
+	assert!(post_model.hooked);
+}